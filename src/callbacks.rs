@@ -1,12 +1,13 @@
 use crate::{
-    events::{FtLockupClaimLockup, FtLockupCreateLockup},
-    lockup::{Lockup, LockupClaim},
-    util::{current_timestamp_sec, ZERO_NEAR},
+    events::{FtLockupClaimLockup, FtLockupRestoreLockup},
+    lockup::{Lockup, LockupClaim, LockupIndex},
+    termination::TerminationRefund,
+    util::ZERO_NEAR,
     Contract, ContractExt, StorageKey,
 };
 use near_sdk::{
-    collections::UnorderedSet, ext_contract, is_promise_success, log, near_bindgen, AccountId,
-    NearToken,
+    collections::UnorderedSet, env, ext_contract, is_promise_success, log, near_bindgen, AccountId,
+    NearToken, PromiseResult,
 };
 use near_sdk_contract_tools::standard::nep297::Event;
 
@@ -18,7 +19,7 @@ pub trait SelfCallbacks {
         lockup_claims: Vec<LockupClaim>,
     ) -> NearToken;
 
-    fn after_lockup_termination(&mut self, account_id: AccountId, amount: NearToken) -> NearToken;
+    fn after_lockups_termination(&mut self, refunds: Vec<TerminationRefund>) -> NearToken;
 }
 
 #[near_bindgen]
@@ -78,7 +79,7 @@ impl SelfCallbacks for Contract {
                 if indices.insert(&index) {
                     modified = true;
                 }
-                let mut lockup = self.lockups.get(index as _).unwrap();
+                let mut lockup = self.internal_get_lockup(index).unwrap();
                 lockup.claimed_balance = lockup.claimed_balance.saturating_sub(claim_amount);
                 self.lockups.replace(index as _, &lockup);
             }
@@ -90,17 +91,142 @@ impl SelfCallbacks for Contract {
         total_balance
     }
 
+    /// The refunds are in the order of the joint transfers, so each promise result
+    /// belongs to the refund with the same index.
+    /// A failed refund, whatever its disposition, rolls back the termination of the lockups
+    /// it's collected from, so they can be terminated again.
     #[private]
-    fn after_lockup_termination(&mut self, account_id: AccountId, amount: NearToken) -> NearToken {
-        if !is_promise_success() {
-            log!("Lockup termination transfer has failed.");
-            // There is no internal balance, so instead we create a new lockup.
-            let lockup = Lockup::new_unlocked_since(account_id, amount, current_timestamp_sec());
-            let lockup_index = self.internal_add_lockup(&lockup);
-            FtLockupCreateLockup::from((lockup_index, lockup)).emit();
-            ZERO_NEAR
-        } else {
-            amount
+    fn after_lockups_termination(&mut self, refunds: Vec<TerminationRefund>) -> NearToken {
+        let mut total_balance = ZERO_NEAR;
+        for (index, refund) in refunds.into_iter().enumerate() {
+            if matches!(
+                env::promise_result(index as _),
+                PromiseResult::Successful(_)
+            ) {
+                total_balance = total_balance.saturating_add(refund.amount);
+            } else {
+                log!(
+                    "Lockup termination transfer to {} has failed.",
+                    refund.beneficiary_id
+                );
+                for (lockup_index, lockup) in refund.lockups {
+                    self.internal_restore_terminated_lockup(lockup_index, lockup);
+                }
+            }
         }
+        total_balance
+    }
+}
+
+impl Contract {
+    /// Puts the lockup back as it was before the termination. The balance claimed since
+    /// is kept, it can't be above the balance kept by the termination.
+    fn internal_restore_terminated_lockup(
+        &mut self,
+        lockup_index: LockupIndex,
+        mut lockup: Lockup,
+    ) {
+        let terminated_lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        lockup.claimed_balance = terminated_lockup.claimed_balance;
+        self.lockups.replace(lockup_index as _, &lockup);
+        // The terminated lockup may have been fully claimed or emptied by the termination.
+        let mut indices = self
+            .account_lockups
+            .get(&lockup.account_id)
+            .unwrap_or(UnorderedSet::new(StorageKey::AccountLockups));
+        indices.insert(&lockup_index);
+        self.internal_save_account_lockups(&lockup.account_id, indices);
+        FtLockupRestoreLockup { id: lockup_index }.emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{schedule::Schedule, termination::TerminationDisposition};
+    use near_sdk::{
+        json_types::U128,
+        test_utils::{accounts, get_logs, VMContextBuilder},
+        test_vm_config, testing_env, RuntimeFeesConfig,
+    };
+
+    fn set_promise_results(promise_results: Vec<PromiseResult>) {
+        let context = VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(100 * 1_000_000_000)
+            .build();
+        testing_env!(
+            context,
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            promise_results
+        );
+    }
+
+    #[test]
+    fn test_after_lockups_termination_restores_failed_lockups() {
+        set_promise_results(vec![
+            PromiseResult::Successful(vec![]),
+            PromiseResult::Failed,
+        ]);
+        let mut contract = Contract::new(accounts(0), vec![], None);
+        let mut refunds = vec![];
+        for account_id in [accounts(1), accounts(2)] {
+            let lockup = Lockup {
+                schedule: Schedule::new_cliff_linear(
+                    NearToken::from_yoctonear(1000),
+                    0.into(),
+                    0.into(),
+                    1000.into(),
+                )
+                .into(),
+                ..Lockup::new_unlocked_since(
+                    account_id.clone(),
+                    NearToken::from_yoctonear(1000),
+                    U128(1),
+                )
+            };
+            let lockup_index = contract.internal_add_lockup(&lockup);
+            // Terminated at 400 and partially claimed since.
+            let mut terminated_lockup = lockup.clone();
+            terminated_lockup
+                .schedule
+                .terminate(NearToken::from_yoctonear(400), U128(400));
+            terminated_lockup.claimed_balance = NearToken::from_yoctonear(100);
+            contract
+                .lockups
+                .replace(lockup_index as _, &terminated_lockup);
+            refunds.push(TerminationRefund {
+                disposition: TerminationDisposition::Beneficiary,
+                beneficiary_id: accounts(0),
+                amount: NearToken::from_yoctonear(600),
+                lockups: vec![(lockup_index, lockup)],
+            });
+        }
+
+        let total_balance = contract.after_lockups_termination(refunds);
+        assert_eq!(total_balance, NearToken::from_yoctonear(600));
+        let lockup = contract.lockups.get(0).unwrap();
+        assert_eq!(
+            lockup.schedule.total_balance(),
+            NearToken::from_yoctonear(400)
+        );
+        // The failed refund is back in the lockup, the claimed balance is kept.
+        let lockup = contract.lockups.get(1).unwrap();
+        assert_eq!(
+            lockup.schedule.total_balance(),
+            NearToken::from_yoctonear(1000)
+        );
+        assert_eq!(lockup.claimed_balance, NearToken::from_yoctonear(100));
+        assert!(get_logs()
+            .last()
+            .unwrap()
+            .contains(r#""event":"ft_lockup_restore_lockup","data":{"id":1}"#));
+        // No new lockups are created.
+        assert_eq!(contract.lockups.len(), 2);
     }
 }
@@ -1,4 +1,8 @@
-use crate::lockup::{Lockup, LockupIndex};
+use crate::{
+    lockup::{Lockup, LockupIndex},
+    schedule::{Acceleration, ScheduleAnchor},
+    template::{TemplateCheckpoint, TemplateId},
+};
 use near_sdk::{json_types::U128, AccountId, NearToken};
 use near_sdk_contract_tools::event;
 
@@ -26,7 +30,14 @@ pub struct FtLockupCreateLockup {
     pub balance: NearToken,
     pub start: U128,
     pub finish: U128,
+    /// What `start` and `finish` are counted from, omitted for unix timestamps.
+    /// The offsets of a TGE-anchored lockup are resolved by the `ft_lockup_set_tge_timestamp` event.
+    #[serde(skip_serializing_if = "ScheduleAnchor::is_absolute")]
+    pub anchor: ScheduleAnchor,
     pub terminatable: bool,
+    /// The excess of the transferred amount returned to the sender.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refunded_balance: Option<NearToken>,
 }
 
 impl From<(LockupIndex, Lockup)> for FtLockupCreateLockup {
@@ -36,13 +47,23 @@ impl From<(LockupIndex, Lockup)> for FtLockupCreateLockup {
             id,
             account_id: lockup.account_id,
             balance: lockup.schedule.total_balance(),
-            start: U128(lockup.schedule.0.first().unwrap().timestamp),
-            finish: U128(lockup.schedule.0.last().unwrap().timestamp),
+            start: U128(lockup.schedule.start_timestamp()),
+            finish: U128(lockup.schedule.finish_timestamp()),
+            anchor: lockup.anchor,
             terminatable: lockup.termination_config.is_some(),
+            refunded_balance: None,
         }
     }
 }
 
+/// The transfer of an invalid lockup message is refunded with the reason.
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupCreateRejected {
+    pub sender_id: AccountId,
+    pub amount: NearToken,
+    pub reason: String,
+}
+
 #[event(version = "1.0.0", standard = "ft-lockup")]
 pub struct FtLockupClaimLockup {
     pub id: LockupIndex,
@@ -60,11 +81,106 @@ pub struct FtLockupTerminateLockup {
     pub id: LockupIndex,
     pub termination_timestamp: U128,
     pub unvested_balance: NearToken,
+    /// The vested balance forfeited by a bad leaver, omitted when nothing is forfeited.
+    #[serde(skip_serializing_if = "NearToken::is_zero")]
+    pub forfeited_balance: NearToken,
+}
+
+/// The termination is rolled back, as the transfer of its refund has failed.
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupRestoreLockup {
+    pub id: LockupIndex,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupAccelerateLockup {
+    pub id: LockupIndex,
+    pub acceleration: Acceleration,
+    /// The finish timestamp of the accelerated schedule.
+    pub finish: U128,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupPauseVesting {
+    pub id: LockupIndex,
+    pub from: U128,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupResumeVesting {
+    pub id: LockupIndex,
+    pub at: U128,
+    /// The finish timestamp of the postponed schedule.
+    pub finish: U128,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupScheduleTermination {
+    pub id: LockupIndex,
+    pub termination_timestamp: U128,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupCancelTermination {
+    pub id: LockupIndex,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupProposeTerminator {
+    pub id: LockupIndex,
+    pub terminator_id: Option<AccountId>,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupAcceptTerminator {
+    pub id: LockupIndex,
+    pub terminator_id: AccountId,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupSetBeneficiary {
+    pub id: LockupIndex,
+    pub beneficiary_id: AccountId,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupRevealVestingSchedule {
+    pub id: LockupIndex,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupRevokeScheduleSignatures {
+    pub id: LockupIndex,
+    /// The nonce the new signatures have to be issued for.
+    pub nonce: u64,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupSetTgeTimestamp {
+    pub tge_timestamp: U128,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupAddTemplate {
+    pub template_id: TemplateId,
+    pub checkpoints: Vec<TemplateCheckpoint>,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupUpdateTemplate {
+    pub template_id: TemplateId,
+    pub checkpoints: Vec<TemplateCheckpoint>,
+}
+
+#[event(version = "1.0.0", standard = "ft-lockup")]
+pub struct FtLockupRemoveTemplate {
+    pub template_id: TemplateId,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::schedule::Interpolation;
     use near_sdk::{
         serde_json,
         serde_json::{json, Value},
@@ -172,6 +288,347 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_ft_lockup_create_lockup_relative_to_tge() {
+        testing_env!(get_context());
+
+        let account_id: AccountId = "alice.near".parse().unwrap();
+        let balance: NearToken = NearToken::from_yoctonear(10_000);
+        let lockup = Lockup {
+            anchor: ScheduleAnchor::Tge,
+            ..Lockup::new_unlocked_since(account_id.clone(), balance, U128(100))
+        };
+
+        FtLockupCreateLockup::from((100, lockup)).emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_create_lockup",
+                "data":
+                    {
+                        "id": 100,
+                        "account_id": account_id,
+                        "balance": balance,
+                        "start": U128(99),
+                        "finish": U128(100),
+                        "anchor": "Tge",
+                        "terminatable": false,
+                    },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_create_rejected() {
+        testing_env!(get_context());
+
+        let sender_id: AccountId = "alice.near".parse().unwrap();
+        let amount = NearToken::from_yoctonear(10_000);
+        FtLockupCreateRejected {
+            sender_id: sender_id.clone(),
+            amount,
+            reason: "expected at least one lockup".to_string(),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_create_rejected",
+                "data":
+                    {
+                        "sender_id": sender_id,
+                        "amount": amount,
+                        "reason": "expected at least one lockup",
+                    },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_accelerate_lockup() {
+        testing_env!(get_context());
+
+        FtLockupAccelerateLockup {
+            id: 100,
+            acceleration: Acceleration::Months(6),
+            finish: U128(1_000),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_accelerate_lockup",
+                "data": { "id": 100, "acceleration": { "Months": 6 }, "finish": "1000" },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_pause_vesting() {
+        testing_env!(get_context());
+
+        FtLockupPauseVesting {
+            id: 100,
+            from: U128(1_000),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_pause_vesting",
+                "data": { "id": 100, "from": "1000" },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_resume_vesting() {
+        testing_env!(get_context());
+
+        FtLockupResumeVesting {
+            id: 100,
+            at: U128(1_000),
+            finish: U128(2_000),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_resume_vesting",
+                "data": { "id": 100, "at": "1000", "finish": "2000" },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_schedule_termination() {
+        testing_env!(get_context());
+
+        FtLockupScheduleTermination {
+            id: 100,
+            termination_timestamp: U128(1_000),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_schedule_termination",
+                "data": { "id": 100, "termination_timestamp": "1000" },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_cancel_termination() {
+        testing_env!(get_context());
+
+        FtLockupCancelTermination { id: 100 }.emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_cancel_termination",
+                "data": { "id": 100 },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_propose_terminator() {
+        testing_env!(get_context());
+
+        FtLockupProposeTerminator {
+            id: 100,
+            terminator_id: Some("alice.near".parse().unwrap()),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_propose_terminator",
+                "data": { "id": 100, "terminator_id": "alice.near" },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_accept_terminator() {
+        testing_env!(get_context());
+
+        FtLockupAcceptTerminator {
+            id: 100,
+            terminator_id: "alice.near".parse().unwrap(),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_accept_terminator",
+                "data": { "id": 100, "terminator_id": "alice.near" },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_set_beneficiary() {
+        testing_env!(get_context());
+
+        FtLockupSetBeneficiary {
+            id: 100,
+            beneficiary_id: "alice.near".parse().unwrap(),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_set_beneficiary",
+                "data": { "id": 100, "beneficiary_id": "alice.near" },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_reveal_vesting_schedule() {
+        testing_env!(get_context());
+
+        FtLockupRevealVestingSchedule { id: 100 }.emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_reveal_vesting_schedule",
+                "data": { "id": 100 },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_restore_lockup() {
+        testing_env!(get_context());
+
+        FtLockupRestoreLockup { id: 100 }.emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_restore_lockup",
+                "data": { "id": 100 },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_revoke_schedule_signatures() {
+        testing_env!(get_context());
+
+        FtLockupRevokeScheduleSignatures { id: 100, nonce: 1 }.emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_revoke_schedule_signatures",
+                "data": { "id": 100, "nonce": 1 },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_set_tge_timestamp() {
+        testing_env!(get_context());
+
+        FtLockupSetTgeTimestamp {
+            tge_timestamp: U128(1_700_000_000),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_set_tge_timestamp",
+                "data": { "tge_timestamp": "1700000000" },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_add_template() {
+        testing_env!(get_context());
+
+        FtLockupAddTemplate {
+            template_id: "cliff".to_string(),
+            checkpoints: vec![
+                TemplateCheckpoint {
+                    offset: 0,
+                    basis_points: 0,
+                    interpolation: Interpolation::Linear,
+                },
+                TemplateCheckpoint {
+                    offset: 100,
+                    basis_points: 10_000,
+                    interpolation: Interpolation::StepAtEnd,
+                },
+            ],
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_add_template",
+                "data": {
+                    "template_id": "cliff",
+                    "checkpoints": [
+                        { "offset": 0, "basis_points": 0 },
+                        { "offset": 100, "basis_points": 10_000, "interpolation": "StepAtEnd" },
+                    ],
+                },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_remove_template() {
+        testing_env!(get_context());
+
+        FtLockupRemoveTemplate {
+            template_id: "cliff".to_string(),
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_remove_template",
+                "data": { "template_id": "cliff" },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
     #[test]
     fn test_ft_lockup_claim_lockup() {
         testing_env!(get_context());
@@ -203,6 +660,39 @@ mod tests {
     fn test_ft_lockup_terminate_lockup() {
         testing_env!(get_context());
 
+        let lockup_id: LockupIndex = 100;
+        let termination_timestamp = U128(1_800_000_000);
+        let unvested_balance = NearToken::from_yoctonear(10_000);
+        let forfeited_balance = NearToken::from_yoctonear(500);
+
+        FtLockupTerminateLockup {
+            id: lockup_id,
+            termination_timestamp,
+            unvested_balance,
+            forfeited_balance,
+        }
+        .emit();
+        assert_equal_logs(
+            json!({
+                "standard": PACKAGE_NAME,
+                "version": VERSION,
+                "event": "ft_lockup_terminate_lockup",
+                "data":
+                    {
+                        "id": lockup_id,
+                        "termination_timestamp": termination_timestamp,
+                        "unvested_balance": unvested_balance,
+                        "forfeited_balance": forfeited_balance,
+                    },
+            }),
+            &test_utils::get_logs()[0],
+        )
+    }
+
+    #[test]
+    fn test_ft_lockup_terminate_lockup_nothing_forfeited() {
+        testing_env!(get_context());
+
         let lockup_id: LockupIndex = 100;
         let termination_timestamp = U128(1_800_000_000);
         let unvested_balance = NearToken::from_yoctonear(10_000);
@@ -211,6 +701,7 @@ mod tests {
             id: lockup_id,
             termination_timestamp,
             unvested_balance,
+            forfeited_balance: NearToken::from_yoctonear(0),
         }
         .emit();
         assert_equal_logs(
@@ -1,8 +1,12 @@
-use crate::{events::FtLockupCreateLockup, lockup::LockupCreate, Contract, ContractExt};
-use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
-use near_sdk::{
-    env, json_types::U128, log, near, serde_json, AccountId, NearToken, PromiseOrValue,
+use crate::{
+    events::{FtLockupCreateLockup, FtLockupCreateRejected},
+    lockup::{Lockup, LockupCreate},
+    template::TemplateLockupCreate,
+    util::{ensure, ZERO_NEAR},
+    Contract, ContractExt, GAS_FOR_LOCKUP_CREATE, MAX_LOCKUPS_PER_BATCH,
 };
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::{env, json_types::U128, log, near, AccountId, NearToken, PromiseOrValue};
 use near_sdk_contract_tools::standard::nep297::Event;
 
 #[near]
@@ -19,32 +23,123 @@ impl FungibleTokenReceiver for Contract {
             "Invalid token ID"
         );
         self.assert_deposit_allowlist(&sender_id);
-        let amount = NearToken::from_yoctonear(amount.0);
-        // TODO - Should we catch this parse failure and return amount to sender?
-        let lockup_create: LockupCreate = serde_json::from_str(&msg).unwrap();
-        let lockup = lockup_create.into_lockup(&sender_id);
-        lockup.assert_valid(amount);
-        let index = self.internal_add_lockup(&lockup);
-        log!(
-            "Created new lockup for {} with index {}",
-            lockup.account_id,
-            index
-        );
-        FtLockupCreateLockup::from((index, lockup)).emit();
-        PromiseOrValue::Value(0.into())
+        let (lockups, excess) = match self.internal_parse_lockups(
+            &sender_id,
+            NearToken::from_yoctonear(amount.0),
+            &msg,
+        ) {
+            Ok(parsed) => parsed,
+            Err(reason) => {
+                FtLockupCreateRejected {
+                    sender_id,
+                    amount: NearToken::from_yoctonear(amount.0),
+                    reason,
+                }
+                .emit();
+                // The whole amount is unused, so it's refunded by the token.
+                return PromiseOrValue::Value(amount);
+            }
+        };
+        for lockup in lockups {
+            let index = self.internal_add_lockup(&lockup);
+            log!(
+                "Created new lockup for {} with index {}",
+                lockup.account_id,
+                index
+            );
+            FtLockupCreateLockup {
+                refunded_balance: Some(excess).filter(|excess| !excess.is_zero()),
+                ..FtLockupCreateLockup::from((index, lockup))
+            }
+            .emit();
+        }
+        PromiseOrValue::Value(excess.as_yoctonear().into())
+    }
+}
+
+impl Contract {
+    /// Parses and validates the lockups of the message together with the excess of the amount
+    /// to refund. An invalid message is returned as an error, so the transfer is refunded
+    /// instead of failing. A template is expanded against the whole amount.
+    fn internal_parse_lockups(
+        &mut self,
+        sender_id: &AccountId,
+        amount: NearToken,
+        msg: &str,
+    ) -> Result<(Vec<Lockup>, NearToken), String> {
+        let Some(template_create) = TemplateLockupCreate::from_msg(msg) else {
+            let lockup_creates = LockupCreate::from_msg(msg).map_err(|error| error.to_string())?;
+            return validate_lockups(sender_id, amount, lockup_creates);
+        };
+        let template_create = template_create.map_err(|error| error.to_string())?;
+        let template = self
+            .templates
+            .get(&template_create.template_id)
+            .ok_or_else(|| format!("Template {} not found", template_create.template_id))?;
+        let lockup_create = template_create.to_lockup_create(&template, amount)?;
+        let parsed = validate_lockups(sender_id, amount, vec![lockup_create])?;
+        self.internal_use_template(&template_create.template_id);
+        Ok(parsed)
+    }
+}
+
+/// Validates the lockups against the transferred amount, see `Contract::internal_parse_lockups`.
+fn validate_lockups(
+    sender_id: &AccountId,
+    amount: NearToken,
+    lockup_creates: Vec<LockupCreate>,
+) -> Result<(Vec<Lockup>, NearToken), String> {
+    ensure!(!lockup_creates.is_empty(), "expected at least one lockup");
+    ensure!(
+        lockup_creates.len() <= MAX_LOCKUPS_PER_BATCH,
+        format!("expected at most {} lockups", MAX_LOCKUPS_PER_BATCH)
+    );
+    ensure!(
+        env::prepaid_gas().saturating_sub(env::used_gas()).as_gas()
+            >= GAS_FOR_LOCKUP_CREATE.as_gas() * lockup_creates.len() as u64,
+        "Not enough gas to create the lockups"
+    );
+    let transferred_amount = Some(amount).filter(|_| lockup_creates.len() == 1);
+    let lockups = lockup_creates
+        .iter()
+        .map(|lockup_create| lockup_create.try_into_lockup(sender_id, transferred_amount))
+        .collect::<Result<Vec<Lockup>, String>>()?;
+    if let ([lockup_create], [lockup]) = (lockup_creates.as_slice(), lockups.as_slice()) {
+        let total_balance = lockup.schedule.total_balance();
+        if lockup_create.refund_excess && amount > total_balance {
+            return Ok((lockups, amount.saturating_sub(total_balance)));
+        }
+        lockup.validate(amount)?;
+    } else {
+        ensure!(
+            lockup_creates
+                .iter()
+                .all(|lockup_create| !lockup_create.refund_excess),
+            "refund_excess is only supported for a single lockup"
+        );
+        let total_balance = lockups.iter().fold(ZERO_NEAR, |total_balance, lockup| {
+            total_balance.saturating_add(lockup.schedule.total_balance())
+        });
+        ensure!(
+            total_balance == amount,
+            "The total balance of the lockups doesn't match the transferred amount"
+        );
     }
+    Ok((lockups, ZERO_NEAR))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        schedule::{Checkpoint, Schedule},
+        schedule::{Checkpoint, Interpolation, Schedule, ScheduleAnchor, MAX_TGE_TIMESTAMP},
+        template::TemplateCheckpoint,
+        termination::VestingConditions,
         util::ZERO_NEAR,
     };
     use near_sdk::{
-        require,
-        test_utils::{accounts, VMContextBuilder},
+        require, serde_json,
+        test_utils::{accounts, get_logs, VMContextBuilder},
         testing_env,
     };
 
@@ -62,20 +157,29 @@ mod tests {
         let context = get_context(accounts(0));
         let one_near = NearToken::from_near(1);
         testing_env!(context.build());
-        let mut contract = Contract::new(accounts(0), vec![accounts(1)]);
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
         let lockup_create = LockupCreate {
             account_id: "x.near".parse().unwrap(),
             schedule: Schedule(vec![
                 Checkpoint {
                     timestamp: 0,
                     balance: ZERO_NEAR,
+                    interpolation: Interpolation::Linear,
                 },
                 Checkpoint {
                     timestamp: 1,
                     balance: one_near,
+                    interpolation: Interpolation::Linear,
                 },
-            ]),
+            ])
+            .into(),
             vesting_schedule: None,
+            anchor: ScheduleAnchor::Absolute,
+            beneficiary_id: None,
+            terminator_id: None,
+            termination_policy: None,
+            termination_disposition: None,
+            refund_excess: false,
         };
         let value = contract.ft_on_transfer(
             accounts(1),
@@ -88,12 +192,539 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ft_on_transfer_refund_excess() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let lockup_create = LockupCreate {
+            refund_excess: true,
+            ..LockupCreate::new_unlocked("x.near".parse().unwrap(), NearToken::from_yoctonear(7))
+        };
+        let value = contract.ft_on_transfer(
+            accounts(1),
+            U128(10),
+            serde_json::to_string(&lockup_create).unwrap(),
+        );
+        require!(
+            matches!(value, PromiseOrValue::Value(v) if v.0 == 3),
+            "failed expectation!"
+        );
+        let lockup = contract.lockups.get(0).unwrap();
+        assert_eq!(lockup.schedule.total_balance().as_yoctonear(), 7);
+        assert!(get_logs()
+            .last()
+            .unwrap()
+            .contains("\"refunded_balance\":\"3\""));
+    }
+
+    #[test]
+    fn test_ft_on_transfer_refund_excess_batch() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let mut lockup_creates = batch_lockup_creates(2);
+        lockup_creates[0].refund_excess = true;
+        let result = contract.ft_on_transfer(
+            accounts(1),
+            U128(3),
+            serde_json::to_string(&lockup_creates).unwrap(),
+        );
+        assert_rejected(
+            &contract,
+            result,
+            3,
+            "refund_excess is only supported for a single lockup",
+        );
+    }
+
+    fn batch_lockup_creates(count: u128) -> Vec<LockupCreate> {
+        (1..=count)
+            .map(|amount| {
+                LockupCreate::new_unlocked(
+                    format!("x{}.near", amount).parse().unwrap(),
+                    NearToken::from_yoctonear(amount),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ft_on_transfer_batch() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let value = contract.ft_on_transfer(
+            accounts(1),
+            U128(6),
+            serde_json::to_string(&batch_lockup_creates(3)).unwrap(),
+        );
+        require!(
+            matches!(value, PromiseOrValue::Value(v) if v.0 == 0),
+            "failed expectation!"
+        );
+        assert_eq!(contract.get_num_lockups(), 3);
+        let lockup = contract.get_lockup(2).unwrap();
+        assert_eq!(lockup.account_id.as_str(), "x3.near");
+        assert_eq!(lockup.total_balance, NearToken::from_yoctonear(3));
+        let logs = near_sdk::test_utils::get_logs();
+        assert_eq!(
+            logs.iter()
+                .filter(|log| log.contains(r#""event":"ft_lockup_create_lockup""#))
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_ft_on_transfer_batch_wrong_amount() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let result = contract.ft_on_transfer(
+            accounts(1),
+            U128(7),
+            serde_json::to_string(&batch_lockup_creates(3)).unwrap(),
+        );
+        assert_rejected(
+            &contract,
+            result,
+            7,
+            "The total balance of the lockups doesn't match the transferred amount",
+        );
+    }
+
+    #[test]
+    fn test_ft_on_transfer_batch_too_large() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let result = contract.ft_on_transfer(
+            accounts(1),
+            U128((1..=101).sum()),
+            serde_json::to_string(&batch_lockup_creates(101)).unwrap(),
+        );
+        assert_rejected(
+            &contract,
+            result,
+            (1..=101).sum(),
+            "expected at most 100 lockups",
+        );
+    }
+
+    /// The whole amount is returned and the rejection is logged without any lockup.
+    fn assert_rejected(
+        contract: &Contract,
+        result: PromiseOrValue<U128>,
+        amount: u128,
+        reason: &str,
+    ) {
+        match result {
+            PromiseOrValue::Value(refund) => assert_eq!(refund.0, amount),
+            PromiseOrValue::Promise(_) => panic!("expected a refund"),
+        }
+        assert_eq!(contract.lockups.len(), 0);
+        let logs = get_logs();
+        let event = logs.last().unwrap();
+        assert!(event.contains("\"event\":\"ft_lockup_create_rejected\""));
+        assert!(event.contains(reason), "{}", event);
+    }
+
+    #[test]
+    fn test_ft_on_transfer_malformed_msg() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let result = contract.ft_on_transfer(accounts(1), U128(5), "{\"account_id\":".to_string());
+        assert_rejected(&contract, result, 5, "EOF while parsing");
+    }
+
+    #[test]
+    fn test_ft_on_transfer_calendar_monthly() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let msg = r#"{
+            "account_id": "x.near",
+            "schedule": {
+                "CalendarMonthly": {
+                    "total_balance": "12",
+                    "start": 1703980800,
+                    "cliff_months": 0,
+                    "months": 12,
+                    "day_of_month": 1
+                }
+            }
+        }"#;
+        contract.ft_on_transfer(accounts(1), U128(12), msg.to_string());
+        let lockup = contract.lockups.get(0).unwrap();
+        assert_eq!(
+            lockup.schedule.to_schedule(),
+            Schedule::new_calendar_monthly(
+                NearToken::from_yoctonear(12),
+                1_703_980_800.into(),
+                0,
+                12,
+                1
+            )
+        );
+        // 2024-12-01
+        assert_eq!(lockup.schedule.finish_timestamp(), 1_733_011_200);
+    }
+
+    #[test]
+    fn test_ft_on_transfer_relative_to_creation() {
+        let mut context = get_context(accounts(0));
+        // 2024-01-15 10:00 UTC
+        let now = 1_705_312_800;
+        testing_env!(context.block_timestamp(now * 1_000_000_000).build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let msg = r#"{
+            "account_id": "x.near",
+            "schedule": {
+                "CliffLinear": {
+                    "total_balance": "1000",
+                    "start": 0,
+                    "cliff": 100,
+                    "finish": 1000
+                }
+            },
+            "vesting_schedule": {
+                "Schedule": [
+                    {"timestamp": 0, "balance": "0"},
+                    {"timestamp": 1, "balance": "1000"}
+                ]
+            },
+            "anchor": "Creation"
+        }"#;
+        contract.ft_on_transfer(accounts(1), U128(1000), msg.to_string());
+        let lockup = contract.lockups.get(0).unwrap();
+        assert_eq!(lockup.schedule.start_timestamp(), now as u128);
+        assert_eq!(lockup.schedule.finish_timestamp(), now as u128 + 1000);
+        match lockup.termination_config.unwrap().vesting_schedule {
+            VestingConditions::Schedule(schedule) => {
+                assert_eq!(schedule.start_timestamp(), now as u128);
+                assert_eq!(schedule.finish_timestamp(), now as u128 + 1);
+            }
+            _ => panic!("expected vesting schedule"),
+        }
+        let logs = near_sdk::test_utils::get_logs();
+        assert!(logs
+            .last()
+            .unwrap()
+            .contains(r#""start":"1705312800","finish":"1705313800""#));
+    }
+
+    #[test]
+    fn test_ft_on_transfer_relative_to_creation_hashed() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let lockup_create = LockupCreate {
+            vesting_schedule: Some(VestingConditions::Hash([0; 32].into())),
+            anchor: ScheduleAnchor::Creation,
+            ..LockupCreate::new_unlocked("x.near".parse().unwrap(), NearToken::from_yoctonear(1))
+        };
+        let result = contract.ft_on_transfer(
+            accounts(1),
+            U128(1),
+            serde_json::to_string(&lockup_create).unwrap(),
+        );
+        assert_rejected(
+            &contract,
+            result,
+            1,
+            "hashed vesting schedule can't be relative to the lockup creation",
+        );
+    }
+
+    #[test]
+    fn test_ft_on_transfer_relative_to_tge() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let lockup_create = LockupCreate {
+            anchor: ScheduleAnchor::Tge,
+            ..LockupCreate::new_unlocked("x.near".parse().unwrap(), NearToken::from_yoctonear(1))
+        };
+        contract.ft_on_transfer(
+            accounts(1),
+            U128(1),
+            serde_json::to_string(&lockup_create).unwrap(),
+        );
+        let view = contract.get_lockup(0).unwrap();
+        assert!(!view.anchor_set);
+        assert_eq!(view.unclaimed_balance, ZERO_NEAR);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_timestamp(100 * 1_000_000_000)
+            .build());
+        contract.set_tge_timestamp(U128(50));
+        assert_eq!(contract.get_tge_timestamp(), Some(U128(50)));
+        let view = contract.get_lockup(0).unwrap();
+        assert!(view.anchor_set);
+        assert_eq!(view.unclaimed_balance, NearToken::from_yoctonear(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "TGE timestamp is already set")]
+    fn test_set_tge_timestamp_twice() {
+        let context = get_context(accounts(1));
+        testing_env!(context
+            .clone()
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        contract.set_tge_timestamp(U128(50));
+        contract.set_tge_timestamp(U128(60));
+    }
+
+    #[test]
+    #[should_panic(expected = "TGE timestamp can't exceed u64::MAX")]
+    fn test_set_tge_timestamp_too_late() {
+        let context = get_context(accounts(1));
+        testing_env!(context
+            .clone()
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        contract.set_tge_timestamp(U128(MAX_TGE_TIMESTAMP + 1));
+    }
+
+    #[test]
+    fn test_ft_on_transfer_share_schedule() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let msg = r#"{
+            "account_id": "x.near",
+            "schedule": {
+                "Shares": {
+                    "unit": "BasisPoints",
+                    "checkpoints": [
+                        { "timestamp": 1000, "share": 0 },
+                        { "timestamp": 2000, "share": 3333 },
+                        { "timestamp": 3000, "share": 10000 }
+                    ]
+                }
+            },
+            "vesting_schedule": "SameAsLockupSchedule"
+        }"#;
+        let value = contract.ft_on_transfer(accounts(1), U128(7), msg.to_string());
+        require!(
+            matches!(value, PromiseOrValue::Value(v) if v.0 == 0),
+            "failed expectation!"
+        );
+        let lockup = contract.lockups.get(0).unwrap();
+        assert_eq!(
+            lockup
+                .schedule
+                .to_schedule()
+                .0
+                .iter()
+                .map(|checkpoint| (checkpoint.timestamp, checkpoint.balance.as_yoctonear()))
+                .collect::<Vec<_>>(),
+            vec![(1000, 0), (2000, 2), (3000, 7)]
+        );
+        assert!(lockup.termination_config.is_some());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_share_schedule_batch() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        let msg = r#"[{
+            "account_id": "x.near",
+            "schedule": {
+                "Shares": {
+                    "unit": "BasisPoints",
+                    "checkpoints": [{ "timestamp": 1000, "share": 10000 }]
+                }
+            }
+        }, {
+            "account_id": "y.near",
+            "schedule": [{ "timestamp": 1000, "balance": "1" }]
+        }]"#;
+        let result = contract.ft_on_transfer(accounts(1), U128(7), msg.to_string());
+        assert_rejected(
+            &contract,
+            result,
+            7,
+            "a schedule in shares is only supported for a single lockup",
+        );
+    }
+
+    /// A contract with a template added by the allowlisted `accounts(1)`.
+    fn contract_with_template(template_id: &str) -> Contract {
+        testing_env!(get_context(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        let mut contract = Contract::new(accounts(0), vec![accounts(1)], None);
+        contract.add_template(
+            template_id.to_string(),
+            vec![
+                TemplateCheckpoint {
+                    offset: 0,
+                    basis_points: 0,
+                    interpolation: Interpolation::Linear,
+                },
+                TemplateCheckpoint {
+                    offset: 100,
+                    basis_points: 2_500,
+                    interpolation: Interpolation::StepAtEnd,
+                },
+                TemplateCheckpoint {
+                    offset: 400,
+                    basis_points: 10_000,
+                    interpolation: Interpolation::Linear,
+                },
+            ],
+        );
+        contract
+    }
+
+    #[test]
+    fn test_ft_on_transfer_template() {
+        let mut contract = contract_with_template("cliff");
+        testing_env!(get_context(accounts(0)).build());
+        let msg = r#"{"template_id": "cliff", "account_id": "x.near", "start": 1000}"#;
+        let value = contract.ft_on_transfer(accounts(1), U128(1001), msg.to_string());
+        require!(
+            matches!(value, PromiseOrValue::Value(v) if v.0 == 0),
+            "failed expectation!"
+        );
+        let lockup = contract.lockups.get(0).unwrap();
+        assert_eq!(lockup.account_id.as_str(), "x.near");
+        assert_eq!(
+            lockup.schedule.to_schedule(),
+            Schedule(vec![
+                Checkpoint {
+                    timestamp: 1000,
+                    balance: ZERO_NEAR,
+                    interpolation: Interpolation::Linear,
+                },
+                Checkpoint {
+                    timestamp: 1100,
+                    balance: NearToken::from_yoctonear(250),
+                    interpolation: Interpolation::StepAtEnd,
+                },
+                Checkpoint {
+                    timestamp: 1400,
+                    balance: NearToken::from_yoctonear(1001),
+                    interpolation: Interpolation::Linear,
+                },
+            ])
+        );
+        assert_eq!(
+            contract
+                .get_template("cliff".to_string())
+                .unwrap()
+                .num_lockups,
+            1
+        );
+    }
+
+    #[test]
+    fn test_ft_on_transfer_unknown_template() {
+        let mut contract = contract_with_template("cliff");
+        testing_env!(get_context(accounts(0)).build());
+        let msg = r#"{"template_id": "linear", "account_id": "x.near", "start": 1000}"#;
+        let result = contract.ft_on_transfer(accounts(1), U128(1000), msg.to_string());
+        assert_rejected(&contract, result, 1000, "Template linear not found");
+    }
+
+    #[test]
+    fn test_ft_on_transfer_template_timestamp_overflow() {
+        let mut contract = contract_with_template("cliff");
+        contract.add_template(
+            "forever".to_string(),
+            vec![
+                TemplateCheckpoint {
+                    offset: 0,
+                    basis_points: 0,
+                    interpolation: Interpolation::Linear,
+                },
+                TemplateCheckpoint {
+                    offset: u128::MAX - 100,
+                    basis_points: 10_000,
+                    interpolation: Interpolation::Linear,
+                },
+            ],
+        );
+        testing_env!(get_context(accounts(0)).build());
+        let msg = r#"{"template_id": "forever", "account_id": "x.near", "start": 1000}"#;
+        let result = contract.ft_on_transfer(accounts(1), U128(1000), msg.to_string());
+        assert_rejected(&contract, result, 1000, "timestamp overflow");
+        assert_eq!(
+            contract
+                .get_template("forever".to_string())
+                .unwrap()
+                .num_lockups,
+            0
+        );
+    }
+
+    #[test]
+    fn test_update_template() {
+        let mut contract = contract_with_template("cliff");
+        let checkpoints = vec![
+            TemplateCheckpoint {
+                offset: 0,
+                basis_points: 0,
+                interpolation: Interpolation::Linear,
+            },
+            TemplateCheckpoint {
+                offset: 100,
+                basis_points: 10_000,
+                interpolation: Interpolation::Linear,
+            },
+        ];
+        contract.update_template("cliff".to_string(), checkpoints.clone());
+        assert_eq!(
+            contract
+                .get_template("cliff".to_string())
+                .unwrap()
+                .checkpoints,
+            checkpoints
+        );
+        contract.remove_template("cliff".to_string());
+        assert!(contract.get_templates().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "The template is used by lockups and can't be changed")]
+    fn test_remove_used_template() {
+        let mut contract = contract_with_template("cliff");
+        testing_env!(get_context(accounts(0)).build());
+        let msg = r#"{"template_id": "cliff", "account_id": "x.near", "start": 1000}"#;
+        contract.ft_on_transfer(accounts(1), U128(1000), msg.to_string());
+        testing_env!(get_context(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.remove_template("cliff".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Template already exists")]
+    fn test_add_template_twice() {
+        let mut contract = contract_with_template("cliff");
+        let checkpoints = contract
+            .get_template("cliff".to_string())
+            .unwrap()
+            .checkpoints;
+        contract.add_template("cliff".to_string(), checkpoints);
+    }
+
     #[test]
     #[should_panic(expected = "Invalid token ID")]
     fn test_ft_on_transfer_invalid_token() {
         let context = get_context(accounts(0));
         testing_env!(context.build());
-        let mut contract = Contract::new(accounts(1), vec![]);
+        let mut contract = Contract::new(accounts(1), vec![], None);
         contract.ft_on_transfer(accounts(2), U128(1), "".to_string());
     }
 
@@ -102,7 +733,7 @@ mod tests {
     fn test_ft_on_transfer_not_on_allowlist() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(accounts(1), vec![]);
+        let mut contract = Contract::new(accounts(1), vec![], None);
         contract.ft_on_transfer(accounts(2), U128(1), "".to_string());
     }
 }
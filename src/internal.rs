@@ -1,8 +1,20 @@
 use crate::{
+    callbacks,
+    events::FtLockupTerminateLockup,
     lockup::{Lockup, LockupIndex},
-    Contract, StorageKey,
+    template::TemplateId,
+    termination::{
+        Leaver, ScheduleReveal, TerminationDisposition, TerminationOutcome, TerminationRefund,
+    },
+    util::{current_timestamp_sec, ZERO_NEAR},
+    Contract, StorageKey, GAS_FOR_AFTER_FT_TRANSFER, GAS_FOR_FT_TRANSFER, GAS_FOR_LOCKUP_RESTORE,
+    ONE_YOCTO,
 };
-use near_sdk::{collections::UnorderedSet, require, AccountId};
+use near_sdk::{
+    collections::UnorderedSet, env, json_types::U128, require, serde_json, AccountId, NearToken,
+    Promise, PromiseOrValue,
+};
+use near_sdk_contract_tools::standard::nep297::Event;
 use std::collections::HashSet;
 
 impl Contract {
@@ -13,6 +25,253 @@ impl Contract {
         );
     }
 
+    /// The termination can't be backdated, it defaults to now.
+    pub(crate) fn internal_termination_timestamp(
+        &self,
+        termination_timestamp: Option<U128>,
+    ) -> U128 {
+        let current_timestamp = current_timestamp_sec();
+        let termination_timestamp = termination_timestamp.unwrap_or(current_timestamp);
+        require!(
+            termination_timestamp >= current_timestamp,
+            "expected termination_timestamp >= now",
+        );
+        termination_timestamp
+    }
+
+    /// Terminates the lockup and transfers the unvested and the forfeited balance
+    /// to the beneficiary. If the transfer fails, the termination is rolled back.
+    pub(crate) fn internal_terminate(
+        &mut self,
+        lockup_index: LockupIndex,
+        lockup: Lockup,
+        leaver: Leaver,
+        hashed_schedule: Option<ScheduleReveal>,
+        termination_timestamp: U128,
+    ) -> PromiseOrValue<NearToken> {
+        let original_lockup = lockup.clone();
+        let outcome = self.internal_terminate_lockup(
+            lockup_index,
+            lockup,
+            leaver,
+            hashed_schedule,
+            termination_timestamp,
+        );
+        let refund_balance = outcome.refund_balance();
+        if refund_balance.as_yoctonear() == 0 {
+            return PromiseOrValue::Value(ZERO_NEAR);
+        }
+        let refund = TerminationRefund {
+            disposition: outcome.disposition,
+            beneficiary_id: outcome.beneficiary_id,
+            amount: refund_balance,
+            lockups: vec![(lockup_index, original_lockup)],
+        };
+        self.internal_refund_terminations(
+            vec![refund],
+            format!("Terminated lockup #{}", lockup_index),
+        )
+    }
+
+    /// Terminates the lockups and transfers the unvested and the forfeited balances
+    /// with a single transfer per beneficiary and disposition.
+    pub(crate) fn internal_terminate_many(
+        &mut self,
+        lockups: Vec<(LockupIndex, Lockup, Option<ScheduleReveal>)>,
+        termination_timestamp: U128,
+    ) -> PromiseOrValue<NearToken> {
+        // The beneficiaries are kept in the order of the first lockup, so the transfers are deterministic.
+        let mut refunds: Vec<TerminationRefund> = vec![];
+        for (lockup_index, lockup, hashed_schedule) in lockups {
+            let original_lockup = lockup.clone();
+            let outcome = self.internal_terminate_lockup(
+                lockup_index,
+                lockup,
+                Leaver::Good,
+                hashed_schedule,
+                termination_timestamp,
+            );
+            let refund_balance = outcome.refund_balance();
+            if refund_balance.as_yoctonear() == 0 {
+                continue;
+            }
+            match refunds.iter_mut().find(|refund| {
+                refund.disposition == outcome.disposition
+                    && refund.beneficiary_id == outcome.beneficiary_id
+            }) {
+                Some(refund) => {
+                    refund.amount = refund.amount.saturating_add(refund_balance);
+                    refund.lockups.push((lockup_index, original_lockup));
+                }
+                None => refunds.push(TerminationRefund {
+                    disposition: outcome.disposition,
+                    beneficiary_id: outcome.beneficiary_id,
+                    amount: refund_balance,
+                    lockups: vec![(lockup_index, original_lockup)],
+                }),
+            }
+        }
+
+        self.internal_refund_terminations(refunds, "Terminated lockups".to_string())
+    }
+
+    /// Sends the refunds together, a failed one rolls back the termination of its lockups.
+    /// Every refund reserves the gas of its transfer and the callback reserves the gas
+    /// to restore every lockup, so the prepaid gas bounds the number of refunds.
+    fn internal_refund_terminations(
+        &self,
+        refunds: Vec<TerminationRefund>,
+        memo: String,
+    ) -> PromiseOrValue<NearToken> {
+        let num_lockups: usize = refunds.iter().map(|refund| refund.lockups.len()).sum();
+        let required_gas = GAS_FOR_FT_TRANSFER
+            .saturating_mul(refunds.len() as u64)
+            .saturating_add(GAS_FOR_AFTER_FT_TRANSFER)
+            .saturating_add(GAS_FOR_LOCKUP_RESTORE.saturating_mul(num_lockups as u64));
+        require!(
+            env::prepaid_gas().saturating_sub(env::used_gas()) >= required_gas,
+            format!(
+                "Not enough gas for {} refunds of {} lockups, {} TGas required",
+                refunds.len(),
+                num_lockups,
+                required_gas.as_tgas()
+            )
+        );
+        let mut promise: Option<Promise> = None;
+        for refund in &refunds {
+            let transfer = self.internal_dispose(
+                &refund.disposition,
+                &refund.beneficiary_id,
+                refund.amount,
+                memo.clone(),
+            );
+            promise = Some(match promise {
+                Some(promise) => promise.and(transfer),
+                None => transfer,
+            });
+        }
+        match promise {
+            Some(promise) => PromiseOrValue::from(
+                promise.then(
+                    callbacks::callbacks::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_AFTER_FT_TRANSFER.saturating_add(
+                            GAS_FOR_LOCKUP_RESTORE.saturating_mul(num_lockups as u64),
+                        ))
+                        .after_lockups_termination(refunds),
+                ),
+            ),
+            None => PromiseOrValue::Value(ZERO_NEAR),
+        }
+    }
+
+    /// Terminates the lockup, saves it and emits the event.
+    fn internal_terminate_lockup(
+        &mut self,
+        lockup_index: LockupIndex,
+        mut lockup: Lockup,
+        leaver: Leaver,
+        hashed_schedule: Option<ScheduleReveal>,
+        termination_timestamp: U128,
+    ) -> TerminationOutcome {
+        let outcome = lockup.terminate_as(
+            lockup_index,
+            leaver,
+            hashed_schedule,
+            termination_timestamp,
+            self.rounding,
+            self.tge_timestamp,
+        );
+        self.lockups.replace(lockup_index as _, &lockup);
+
+        // no need to store empty lockup
+        if lockup.schedule.total_balance() == ZERO_NEAR {
+            let lockup_account_id: AccountId = lockup.account_id;
+            let mut indices = self
+                .account_lockups
+                .get(&lockup_account_id)
+                .unwrap_or(UnorderedSet::new(StorageKey::AccountLockups));
+            indices.remove(&lockup_index);
+            self.internal_save_account_lockups(&lockup_account_id, indices);
+        }
+
+        FtLockupTerminateLockup {
+            id: lockup_index,
+            termination_timestamp,
+            unvested_balance: outcome.unvested_balance,
+            forfeited_balance: outcome.forfeited_balance,
+        }
+        .emit();
+        outcome
+    }
+
+    /// Transfers the refund to the beneficiary or the treasury, or burns it.
+    fn internal_dispose(
+        &self,
+        disposition: &TerminationDisposition,
+        beneficiary_id: &AccountId,
+        amount: NearToken,
+        memo: String,
+    ) -> Promise {
+        match disposition {
+            TerminationDisposition::Beneficiary => {
+                self.internal_ft_transfer(beneficiary_id, amount, memo)
+            }
+            TerminationDisposition::Treasury { account_id } => {
+                self.internal_ft_transfer(account_id, amount, memo)
+            }
+            TerminationDisposition::Burn => self.internal_ft_burn(amount, memo),
+        }
+    }
+
+    fn internal_ft_burn(&self, amount: NearToken, memo: String) -> Promise {
+        Promise::new(self.token_id.clone()).function_call(
+            "ft_burn".to_string(),
+            serde_json::json!({
+                "amount": amount,
+                "memo": Some(memo)
+            })
+            .to_string()
+            .into_bytes(),
+            ONE_YOCTO,
+            GAS_FOR_FT_TRANSFER,
+        )
+    }
+
+    fn internal_ft_transfer(
+        &self,
+        receiver_id: &AccountId,
+        amount: NearToken,
+        memo: String,
+    ) -> Promise {
+        Promise::new(self.token_id.clone()).function_call(
+            "ft_transfer".to_string(),
+            serde_json::json!({
+                "receiver_id": receiver_id,
+                "amount": amount,
+                "memo": Some(memo)
+            })
+            .to_string()
+            .into_bytes(),
+            ONE_YOCTO,
+            GAS_FOR_FT_TRANSFER,
+        )
+    }
+
+    pub(crate) fn assert_template_unused(&self, template_id: &TemplateId) {
+        let template = self.templates.get(template_id).expect("Template not found");
+        require!(
+            !template.is_used(),
+            "The template is used by lockups and can't be changed"
+        );
+    }
+
+    /// Counts a lockup created from the template, it can't be changed anymore.
+    pub(crate) fn internal_use_template(&mut self, template_id: &TemplateId) {
+        let mut template = self.templates.get(template_id).expect("Template not found");
+        template.num_lockups += 1;
+        self.templates.insert(template_id, &template);
+    }
+
     pub(crate) fn internal_add_lockup(&mut self, lockup: &Lockup) -> LockupIndex {
         let index = self.lockups.len() as LockupIndex;
         self.lockups.push(lockup);
@@ -37,6 +296,15 @@ impl Contract {
         }
     }
 
+    /// Reads the lockup, which has to be converted by `migrate_lockups` already.
+    pub(crate) fn internal_get_lockup(&self, lockup_index: LockupIndex) -> Option<Lockup> {
+        require!(
+            !self.is_unmigrated(lockup_index),
+            "lockup is not migrated yet"
+        );
+        self.lockups.get(lockup_index)
+    }
+
     pub(crate) fn internal_get_account_lockups(
         &self,
         account_id: &AccountId,
@@ -45,7 +313,12 @@ impl Contract {
             .get(account_id)
             .unwrap_or(UnorderedSet::new(StorageKey::AccountLockups))
             .iter()
-            .map(|lockup_index| (lockup_index, self.lockups.get(lockup_index as _).unwrap()))
+            .map(|lockup_index| {
+                (
+                    lockup_index,
+                    self.internal_get_lockup(lockup_index).unwrap(),
+                )
+            })
             .collect()
     }
 
@@ -66,7 +339,7 @@ impl Contract {
                     account_lockup_ids.contains(&lockup_index),
                     format!("lockup not found for account: {}", lockup_index),
                 );
-                let lockup = self.lockups.get(lockup_index).unwrap();
+                let lockup = self.internal_get_lockup(lockup_index).unwrap();
                 (lockup_index, lockup)
             })
             .collect()
@@ -1,26 +1,39 @@
 use borsh::BorshSerialize;
 use near_sdk::{
     assert_one_yocto,
-    collections::{LookupMap, UnorderedSet, Vector},
+    collections::{LookupMap, UnorderedMap, UnorderedSet, Vector},
     env,
     json_types::U128,
     log, near, require, serde_json, AccountId, BorshStorageKey, Gas, NearToken, PanicOnDefault,
     Promise, PromiseOrValue,
 };
 use near_sdk_contract_tools::standard::nep297::Event;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub mod callbacks;
 pub mod events;
 pub mod ft_token_receiver;
 pub mod internal;
 pub mod lockup;
+pub mod merkle;
+pub mod migration;
 pub mod schedule;
+pub mod template;
 pub mod termination;
+#[cfg(test)]
+mod test_utils;
 pub mod util;
 pub mod view;
 
-use crate::{events::*, lockup::*, schedule::*, util::*};
+use crate::{
+    events::*,
+    lockup::*,
+    migration::MigrationProgress,
+    schedule::*,
+    template::{ScheduleTemplate, TemplateCheckpoint, TemplateId},
+    termination::{Leaver, ScheduleReveal},
+    util::*,
+};
 
 pub type TokenAccountId = AccountId;
 
@@ -29,6 +42,15 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const GAS_FOR_FT_TRANSFER: Gas = Gas::from_gas(15_000_000_000_000);
 const GAS_FOR_AFTER_FT_TRANSFER: Gas = Gas::from_gas(20_000_000_000_000);
+/// The gas the termination callback needs for every lockup it may have to restore.
+const GAS_FOR_LOCKUP_RESTORE: Gas = Gas::from_gas(1_000_000_000_000);
+/// The gas that has to be left in `ft_on_transfer` for every lockup of a batch.
+const GAS_FOR_LOCKUP_CREATE: Gas = Gas::from_gas(2_000_000_000_000);
+/// The most lockups a single transfer can create or `terminate_many` can terminate.
+/// It bounds the work of a call, the gas of the termination refunds is checked on top
+/// of it: each distinct refund receiver takes `GAS_FOR_FT_TRANSFER`, so a call of 300 TGas
+/// fits about 17 of them.
+const MAX_LOCKUPS_PER_BATCH: usize = 100;
 
 const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
 
@@ -43,8 +65,21 @@ pub struct Contract {
 
     /// account ids that can perform all actions:
     /// - manage deposit_allowlist
-    /// - create lockups, terminate lockups
+    /// - create lockups
     pub deposit_allowlist: UnorderedSet<AccountId>,
+
+    /// How the unlocked balance is rounded in between the schedule checkpoints.
+    pub rounding: Rounding,
+
+    /// The token generation event timestamp the TGE-relative schedules are counted from.
+    /// Can only be set once.
+    pub tge_timestamp: Option<u128>,
+
+    /// The named vesting shapes the lockups can be created from.
+    pub templates: UnorderedMap<TemplateId, ScheduleTemplate>,
+
+    /// The lockups still in the layout of the previous version, see `migrate_lockups`.
+    pub migration: Option<MigrationProgress>,
 }
 
 #[derive(BorshStorageKey, BorshSerialize)]
@@ -52,12 +87,17 @@ pub(crate) enum StorageKey {
     Lockups,
     AccountLockups,
     DepositAllowlist,
+    Templates,
 }
 
 #[near]
 impl Contract {
     #[init]
-    pub fn new(token_id: AccountId, deposit_allowlist: Vec<AccountId>) -> Self {
+    pub fn new(
+        token_id: AccountId,
+        deposit_allowlist: Vec<AccountId>,
+        rounding: Option<Rounding>,
+    ) -> Self {
         let mut deposit_allowlist_set = UnorderedSet::new(StorageKey::DepositAllowlist);
         deposit_allowlist_set.extend(deposit_allowlist.clone());
         FtLockupNew {
@@ -73,6 +113,10 @@ impl Contract {
             account_lockups: LookupMap::new(StorageKey::AccountLockups),
             token_id,
             deposit_allowlist: deposit_allowlist_set,
+            rounding: rounding.unwrap_or_default(),
+            tge_timestamp: None,
+            templates: UnorderedMap::new(StorageKey::Templates),
+            migration: None,
         }
     }
 
@@ -97,13 +141,10 @@ impl Contract {
                         lockup_id,
                         match amount {
                             Some(amount) => amount,
-                            None => {
-                                let lockup =
-                                    lockups_by_id.get(&lockup_id).expect("lockup not found");
-                                let unlocked_balance =
-                                    lockup.schedule.unlocked_balance(current_timestamp_sec());
-                                unlocked_balance.saturating_sub(lockup.claimed_balance)
-                            }
+                            None => lockups_by_id
+                                .get(&lockup_id)
+                                .expect("lockup not found")
+                                .claimable_balance(self.rounding, self.tge_timestamp),
                         },
                     )
                 })
@@ -117,10 +158,7 @@ impl Contract {
             let amounts: HashMap<LockupIndex, NearToken> = lockups_by_id
                 .iter()
                 .map(|(lockup_id, lockup)| {
-                    let unlocked_balance =
-                        lockup.schedule.unlocked_balance(current_timestamp_sec());
-                    let amount: NearToken = unlocked_balance.saturating_sub(lockup.claimed_balance);
-
+                    let amount = lockup.claimable_balance(self.rounding, self.tge_timestamp);
                     (*lockup_id, amount)
                 })
                 .collect();
@@ -132,7 +170,12 @@ impl Contract {
         let mut total_claim_amount = 0;
         for (lockup_index, lockup_claim_amount) in claim_amounts {
             let lockup = lockups_by_id.get_mut(&lockup_index).unwrap();
-            let lockup_claim = lockup.claim(lockup_index, lockup_claim_amount);
+            let lockup_claim = lockup.claim(
+                lockup_index,
+                lockup_claim_amount,
+                self.rounding,
+                self.tge_timestamp,
+            );
 
             if lockup_claim.claim_amount.as_yoctonear() > 0 {
                 log!(
@@ -177,71 +220,361 @@ impl Contract {
         }
     }
 
+    /// Terminates the lockup. `policy` selects the clause of the termination,
+    /// a good leaver by default.
     #[payable]
     pub fn terminate(
         &mut self,
         lockup_index: LockupIndex,
-        hashed_schedule: Option<Schedule>,
+        hashed_schedule: Option<ScheduleReveal>,
         termination_timestamp: Option<U128>,
+        policy: Option<Leaver>,
     ) -> PromiseOrValue<NearToken> {
         assert_one_yocto();
-        self.assert_deposit_allowlist(&env::predecessor_account_id());
+        let lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        lockup.assert_terminator(&env::predecessor_account_id());
+        let termination_timestamp = self.internal_termination_timestamp(termination_timestamp);
+        self.internal_terminate(
+            lockup_index,
+            lockup,
+            policy.unwrap_or_default(),
+            hashed_schedule,
+            termination_timestamp,
+        )
+    }
+
+    /// Terminates many lockups at once, e.g. when a team is let go, as good leavers.
+    /// The refunds are aggregated into a single transfer per beneficiary. If a transfer fails,
+    /// the termination of its lockups is rolled back. The prepaid gas has to cover a transfer
+    /// per beneficiary, see `MAX_LOCKUPS_PER_BATCH`.
+    /// The salted, merkle and signed vesting schedules have to be revealed first.
+    #[payable]
+    pub fn terminate_many(
+        &mut self,
+        terminations: Vec<(LockupIndex, Option<ScheduleSpec>)>,
+        termination_timestamp: Option<U128>,
+    ) -> PromiseOrValue<NearToken> {
+        assert_one_yocto();
+        require!(!terminations.is_empty(), "expected at least one lockup");
+        require!(
+            terminations.len() <= MAX_LOCKUPS_PER_BATCH,
+            format!("expected at most {} lockups", MAX_LOCKUPS_PER_BATCH)
+        );
+        let account_id = env::predecessor_account_id();
+        let termination_timestamp = self.internal_termination_timestamp(termination_timestamp);
+        let mut lockup_indices = HashSet::new();
+        let lockups = terminations
+            .into_iter()
+            .map(|(lockup_index, hashed_schedule)| {
+                require!(
+                    lockup_indices.insert(lockup_index),
+                    format!("duplicate lockup index: {}", lockup_index),
+                );
+                let lockup = self
+                    .internal_get_lockup(lockup_index)
+                    .expect("Lockup not found");
+                lockup.assert_terminator(&account_id);
+                (
+                    lockup_index,
+                    lockup,
+                    hashed_schedule.map(ScheduleReveal::from),
+                )
+            })
+            .collect();
+        self.internal_terminate_many(lockups, termination_timestamp)
+    }
+
+    /// Announces the termination of the lockup at `termination_timestamp`.
+    /// The terminator can cancel it until then, and anyone can finalize it after.
+    /// A hidden vesting schedule is revealed right away.
+    #[payable]
+    pub fn schedule_termination(
+        &mut self,
+        lockup_index: LockupIndex,
+        hashed_schedule: Option<ScheduleReveal>,
+        termination_timestamp: U128,
+        policy: Option<Leaver>,
+    ) {
+        assert_one_yocto();
+        let mut lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        lockup.assert_terminator(&env::predecessor_account_id());
+        if let Some(reveal) = hashed_schedule {
+            lockup.reveal_vesting_schedule(lockup_index, &reveal);
+        }
+        lockup.schedule_termination(
+            lockup_index,
+            policy.unwrap_or_default(),
+            current_timestamp_sec(),
+            termination_timestamp,
+            self.rounding,
+            self.tge_timestamp,
+        );
+        self.lockups.replace(lockup_index as _, &lockup);
+        FtLockupScheduleTermination {
+            id: lockup_index,
+            termination_timestamp,
+        }
+        .emit();
+    }
+
+    #[payable]
+    pub fn cancel_termination(&mut self, lockup_index: LockupIndex) {
+        assert_one_yocto();
         let mut lockup = self
-            .lockups
-            .get(lockup_index as _)
+            .internal_get_lockup(lockup_index)
             .expect("Lockup not found");
+        lockup.assert_terminator(&env::predecessor_account_id());
+        lockup.cancel_termination(current_timestamp_sec());
+        self.lockups.replace(lockup_index as _, &lockup);
+        FtLockupCancelTermination { id: lockup_index }.emit();
+    }
+
+    /// Finalizes the scheduled termination once it's effective. Can be called by anyone.
+    pub fn finalize_termination(&mut self, lockup_index: LockupIndex) -> PromiseOrValue<NearToken> {
+        let lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        let pending_termination = lockup.effective_termination(current_timestamp_sec());
+        self.internal_terminate(
+            lockup_index,
+            lockup,
+            pending_termination.leaver,
+            None,
+            pending_termination.termination_timestamp,
+        )
+    }
+
+    /// Accelerates the vesting of the given lockups, e.g. on a change of control.
+    /// Can only be called by the terminator of every lockup.
+    #[payable]
+    pub fn accelerate(&mut self, lockup_indices: Vec<LockupIndex>, acceleration: Acceleration) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
         let current_timestamp = current_timestamp_sec();
-        let termination_timestamp = termination_timestamp.unwrap_or(current_timestamp);
+        for lockup_index in lockup_indices {
+            let mut lockup = self
+                .internal_get_lockup(lockup_index)
+                .expect("Lockup not found");
+            lockup.assert_terminator(&account_id);
+            lockup.accelerate(acceleration, current_timestamp, self.tge_timestamp);
+            self.lockups.replace(lockup_index as _, &lockup);
+            FtLockupAccelerateLockup {
+                id: lockup_index,
+                acceleration,
+                finish: U128(lockup.schedule.finish_timestamp()),
+            }
+            .emit();
+        }
+    }
+
+    /// Pauses the vesting of the lockup from `from` on, now by default,
+    /// e.g. for an unpaid leave. The balance unlocked by then can still be claimed.
+    #[payable]
+    pub fn pause_vesting(&mut self, lockup_index: LockupIndex, from: Option<U128>) {
+        assert_one_yocto();
+        let mut lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        lockup.assert_terminator(&env::predecessor_account_id());
+        let current_timestamp = current_timestamp_sec();
+        let from = from.unwrap_or(current_timestamp);
+        lockup.pause_vesting(from, current_timestamp, self.tge_timestamp);
+        self.lockups.replace(lockup_index as _, &lockup);
+        FtLockupPauseVesting {
+            id: lockup_index,
+            from,
+        }
+        .emit();
+    }
+
+    /// Resumes the paused vesting of the lockup at `at`, now by default.
+    /// `at` can't be before the start of the pause or in the future.
+    /// The rest of the schedules is moved later by the paused duration.
+    #[payable]
+    pub fn resume_vesting(&mut self, lockup_index: LockupIndex, at: Option<U128>) {
+        assert_one_yocto();
+        let mut lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        lockup.assert_terminator(&env::predecessor_account_id());
+        let now = current_timestamp_sec();
+        let at = at.unwrap_or(now);
+        require!(at <= now, "expected at <= now");
+        lockup.resume_vesting(at);
+        self.lockups.replace(lockup_index as _, &lockup);
+        FtLockupResumeVesting {
+            id: lockup_index,
+            at,
+            finish: U128(lockup.schedule.finish_timestamp()),
+        }
+        .emit();
+    }
+
+    /// Hands the termination authority of the lockup over to another account,
+    /// which has to accept it with `accept_terminator`. `None` cancels the pending handover.
+    #[payable]
+    pub fn propose_terminator(
+        &mut self,
+        lockup_index: LockupIndex,
+        terminator_id: Option<AccountId>,
+    ) {
+        assert_one_yocto();
+        let mut lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        lockup.propose_terminator(&env::predecessor_account_id(), terminator_id.clone());
+        self.lockups.replace(lockup_index as _, &lockup);
+        FtLockupProposeTerminator {
+            id: lockup_index,
+            terminator_id,
+        }
+        .emit();
+    }
+
+    #[payable]
+    pub fn accept_terminator(&mut self, lockup_index: LockupIndex) {
+        assert_one_yocto();
+        let mut lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        let terminator_id = env::predecessor_account_id();
+        lockup.accept_terminator(&terminator_id);
+        self.lockups.replace(lockup_index as _, &lockup);
+        FtLockupAcceptTerminator {
+            id: lockup_index,
+            terminator_id,
+        }
+        .emit();
+    }
+
+    /// Reassigns the termination refunds of one or many lockups to `beneficiary_id`.
+    /// Can be called by the current beneficiary of every lockup.
+    #[payable]
+    pub fn set_beneficiary(&mut self, lockup_indices: Vec<LockupIndex>, beneficiary_id: AccountId) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        for lockup_index in lockup_indices {
+            let mut lockup = self
+                .internal_get_lockup(lockup_index)
+                .expect("Lockup not found");
+            lockup.set_beneficiary(&account_id, beneficiary_id.clone());
+            self.lockups.replace(lockup_index as _, &lockup);
+            FtLockupSetBeneficiary {
+                id: lockup_index,
+                beneficiary_id: beneficiary_id.clone(),
+            }
+            .emit();
+        }
+    }
+
+    /// Replaces the hidden vesting schedule of the lockup with the revealed one.
+    /// Can be called by the lockup account or the termination beneficiary.
+    #[payable]
+    pub fn reveal_vesting_schedule(
+        &mut self,
+        lockup_index: LockupIndex,
+        hashed_schedule: ScheduleReveal,
+    ) {
+        assert_one_yocto();
+        let mut lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        let account_id = env::predecessor_account_id();
         require!(
-            termination_timestamp >= current_timestamp,
-            "expected termination_timestamp >= now",
+            account_id == lockup.account_id
+                || lockup
+                    .termination_config
+                    .as_ref()
+                    .is_some_and(|config| config.beneficiary_id == account_id),
+            "Only the lockup account or the beneficiary can reveal the vesting schedule"
         );
-        let (unvested_balance, beneficiary_id) =
-            lockup.terminate(hashed_schedule, termination_timestamp);
+        lockup.reveal_vesting_schedule(lockup_index, &hashed_schedule);
         self.lockups.replace(lockup_index as _, &lockup);
+        FtLockupRevealVestingSchedule { id: lockup_index }.emit();
+    }
 
-        // no need to store empty lockup
-        if lockup.schedule.total_balance() == ZERO_NEAR {
-            let lockup_account_id: AccountId = lockup.account_id;
-            let mut indices = self
-                .account_lockups
-                .get(&lockup_account_id)
-                .unwrap_or(UnorderedSet::new(StorageKey::AccountLockups));
-            indices.remove(&lockup_index);
-            self.internal_save_account_lockups(&lockup_account_id, indices);
+    /// Bumps the nonce of the signed vesting schedule, so the signatures issued before
+    /// can't be revealed anymore. Can only be called by the terminator.
+    #[payable]
+    pub fn revoke_schedule_signatures(&mut self, lockup_index: LockupIndex) {
+        assert_one_yocto();
+        let mut lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        let nonce = lockup.revoke_schedule_signatures(&env::predecessor_account_id());
+        self.lockups.replace(lockup_index as _, &lockup);
+        FtLockupRevokeScheduleSignatures {
+            id: lockup_index,
+            nonce,
         }
+        .emit();
+    }
 
-        FtLockupTerminateLockup {
-            id: lockup_index,
-            termination_timestamp,
-            unvested_balance,
+    /// Sets the timestamp the TGE-relative schedules are counted from. It can only be set once.
+    #[payable]
+    pub fn set_tge_timestamp(&mut self, tge_timestamp: U128) {
+        assert_one_yocto();
+        self.assert_deposit_allowlist(&env::predecessor_account_id());
+        require!(self.tge_timestamp.is_none(), "TGE timestamp is already set");
+        require!(
+            tge_timestamp.0 <= MAX_TGE_TIMESTAMP,
+            "TGE timestamp can't exceed u64::MAX"
+        );
+        self.tge_timestamp = Some(tge_timestamp.0);
+        FtLockupSetTgeTimestamp { tge_timestamp }.emit();
+    }
+
+    /// Registers a named vesting shape, see `ScheduleTemplate`.
+    #[payable]
+    pub fn add_template(&mut self, template_id: TemplateId, checkpoints: Vec<TemplateCheckpoint>) {
+        assert_one_yocto();
+        self.assert_deposit_allowlist(&env::predecessor_account_id());
+        require!(
+            self.templates.get(&template_id).is_none(),
+            "Template already exists"
+        );
+        let template = ScheduleTemplate::new(checkpoints.clone());
+        expect_valid(template.validate());
+        self.templates.insert(&template_id, &template);
+        FtLockupAddTemplate {
+            template_id,
+            checkpoints,
         }
         .emit();
+    }
 
-        if unvested_balance.as_yoctonear() > 0 {
-            PromiseOrValue::from(
-                Promise::new(self.token_id.clone())
-                    .function_call(
-                        "ft_transfer".to_string(),
-                        serde_json::json!({
-                            "receiver_id": beneficiary_id,
-                            "amount": unvested_balance,
-                            "memo": Some(format!("Terminated lockup #{}", lockup_index))
-                        })
-                        .to_string()
-                        .into_bytes(),
-                        ONE_YOCTO,
-                        GAS_FOR_FT_TRANSFER,
-                    )
-                    .then(
-                        callbacks::callbacks::ext(env::current_account_id())
-                            .with_static_gas(GAS_FOR_AFTER_FT_TRANSFER)
-                            .after_lockup_termination(beneficiary_id, unvested_balance),
-                    ),
-            )
-        } else {
-            PromiseOrValue::Value(ZERO_NEAR)
+    /// Replaces the checkpoints of a template that no lockup was created from.
+    #[payable]
+    pub fn update_template(
+        &mut self,
+        template_id: TemplateId,
+        checkpoints: Vec<TemplateCheckpoint>,
+    ) {
+        assert_one_yocto();
+        self.assert_deposit_allowlist(&env::predecessor_account_id());
+        self.assert_template_unused(&template_id);
+        let template = ScheduleTemplate::new(checkpoints.clone());
+        expect_valid(template.validate());
+        self.templates.insert(&template_id, &template);
+        FtLockupUpdateTemplate {
+            template_id,
+            checkpoints,
         }
+        .emit();
+    }
+
+    /// Removes a template that no lockup was created from.
+    #[payable]
+    pub fn remove_template(&mut self, template_id: TemplateId) {
+        assert_one_yocto();
+        self.assert_deposit_allowlist(&env::predecessor_account_id());
+        self.assert_template_unused(&template_id);
+        self.templates.remove(&template_id);
+        FtLockupRemoveTemplate { template_id }.emit();
     }
 
     // preserving both options for API compatibility
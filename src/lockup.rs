@@ -1,9 +1,15 @@
 use crate::{
-    schedule::Schedule,
-    termination::{TerminationConfig, VestingConditions},
-    util::{current_timestamp_sec, ZERO_NEAR},
+    schedule::{
+        Acceleration, LockupScheduleInput, Schedule, ScheduleAnchor, ScheduleSpec,
+        MAX_TGE_TIMESTAMP,
+    },
+    termination::{
+        PendingTermination, TerminationConfig, TerminationDisposition, TerminationPolicy,
+        VestingConditions,
+    },
+    util::{current_timestamp_sec, ensure, expect_valid, Rounding, ZERO_NEAR},
 };
-use near_sdk::{json_types::U128, near, require, AccountId, NearToken};
+use near_sdk::{json_types::U128, near, require, serde_json, AccountId, CurveType, NearToken};
 
 pub type LockupIndex = u64;
 
@@ -19,10 +25,30 @@ pub struct LockupClaim {
 #[derive(Debug, PartialEq, Clone)]
 pub struct Lockup {
     pub account_id: AccountId,
-    pub schedule: Schedule,
+    pub schedule: ScheduleSpec,
     pub claimed_balance: NearToken,
     /// An optional configuration that allows vesting/lockup termination.
     pub termination_config: Option<TerminationConfig>,
+    /// Schedules relative to the lockup creation are resolved on creation,
+    /// so it's either `Absolute` or `Tge`.
+    #[serde(default, skip_serializing_if = "ScheduleAnchor::is_absolute")]
+    pub anchor: ScheduleAnchor,
+    /// A termination announced ahead of its effective time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_termination: Option<PendingTermination>,
+    /// The intervals the vesting was paused for, the last one is open while it's paused.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paused_intervals: Vec<PausedInterval>,
+}
+
+/// An interval the vesting didn't progress in, e.g. a leave of absence.
+#[near(serializers = [borsh, json])]
+#[derive(Debug, PartialEq, Clone)]
+pub struct PausedInterval {
+    pub from: U128,
+    /// `None` until the vesting is resumed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<U128>,
 }
 
 impl Lockup {
@@ -33,17 +59,201 @@ impl Lockup {
     ) -> Self {
         let lockup = Self {
             account_id,
-            schedule: Schedule::new_unlocked_since(total_balance, timestamp),
+            schedule: Schedule::new_unlocked_since(total_balance, timestamp).into(),
             claimed_balance: NearToken::from_near(0),
             termination_config: None,
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
         };
         // Always validate before construction.
         lockup.assert_valid(total_balance);
         lockup
     }
 
-    pub fn claim(&mut self, index: LockupIndex, claim_amount: NearToken) -> LockupClaim {
-        let unlocked_balance = self.schedule.unlocked_balance(current_timestamp_sec());
+    /// The offset of the schedule from the unix timestamps,
+    /// `None` while the TGE timestamp of a TGE-relative lockup is not set.
+    pub fn schedule_offset(&self, tge_timestamp: Option<u128>) -> Option<u128> {
+        match self.anchor {
+            ScheduleAnchor::Tge => tge_timestamp,
+            ScheduleAnchor::Absolute | ScheduleAnchor::Creation => Some(0),
+        }
+    }
+
+    /// The schedule in unix timestamps, `None` while the TGE timestamp is not set.
+    pub fn resolved_schedule(&self, tge_timestamp: Option<u128>) -> Option<ScheduleSpec> {
+        self.schedule_offset(tge_timestamp).map(|offset| {
+            let mut schedule = self.schedule.clone();
+            schedule.shift(offset);
+            schedule
+        })
+    }
+
+    /// The start of the pause while the vesting is paused.
+    pub fn paused_since(&self) -> Option<U128> {
+        self.paused_intervals
+            .last()
+            .filter(|interval| interval.to.is_none())
+            .map(|interval| interval.from)
+    }
+
+    /// Nothing unlocks or vests after the start of the pause until the vesting is resumed.
+    pub fn paused_timestamp(&self, current_timestamp: U128) -> U128 {
+        match self.paused_since() {
+            Some(from) => std::cmp::min(current_timestamp, from),
+            None => current_timestamp,
+        }
+    }
+
+    /// Nothing unlocks or vests after the time of the scheduled termination either,
+    /// so the balances at the finalization are the ones at the termination.
+    pub fn effective_timestamp(&self, current_timestamp: U128) -> U128 {
+        let current_timestamp = self.paused_timestamp(current_timestamp);
+        match &self.pending_termination {
+            Some(pending_termination) => {
+                std::cmp::min(current_timestamp, pending_termination.termination_timestamp)
+            }
+            None => current_timestamp,
+        }
+    }
+
+    /// Whether a bad leaver termination is scheduled, the lockup can't be claimed until
+    /// it's finalized or cancelled, as the vested balance may be forfeited.
+    pub fn is_bad_leaver_pending(&self) -> bool {
+        self.pending_termination
+            .as_ref()
+            .is_some_and(|pending_termination| !pending_termination.leaver.is_good())
+    }
+
+    pub fn unlocked_balance(
+        &self,
+        current_timestamp: U128,
+        rounding: Rounding,
+        tge_timestamp: Option<u128>,
+    ) -> NearToken {
+        let current_timestamp = self.effective_timestamp(current_timestamp);
+        self.resolved_schedule(tge_timestamp)
+            .map(|schedule| schedule.unlocked_balance(current_timestamp, rounding))
+            .unwrap_or(ZERO_NEAR)
+    }
+
+    /// Accelerates the lockup schedule and the vesting schedule the same way.
+    /// The schedules are resolved to unix timestamps first.
+    pub fn accelerate(
+        &mut self,
+        acceleration: Acceleration,
+        current_timestamp: U128,
+        tge_timestamp: Option<u128>,
+    ) {
+        require!(
+            self.paused_since().is_none(),
+            "The vesting has to be resumed before the acceleration"
+        );
+        let offset = self
+            .schedule_offset(tge_timestamp)
+            .expect("The schedule can't be accelerated before the TGE timestamp is set");
+        require!(
+            !self
+                .termination_config
+                .as_ref()
+                .is_some_and(|config| config.vesting_schedule.is_hidden()),
+            "The hidden vesting schedule has to be revealed before the acceleration"
+        );
+        let total_balance = self.schedule.total_balance();
+        self.schedule.shift(offset);
+        expect_valid(self.schedule.accelerate(current_timestamp, acceleration));
+        self.schedule.assert_valid(total_balance);
+        self.anchor = ScheduleAnchor::Absolute;
+        if let Some(TerminationConfig {
+            vesting_schedule: VestingConditions::Schedule(schedule),
+            ..
+        }) = &mut self.termination_config
+        {
+            schedule.shift(offset);
+            expect_valid(schedule.accelerate(current_timestamp, acceleration));
+            schedule.assert_valid(total_balance);
+            self.schedule.assert_valid_termination_schedule(schedule);
+        }
+    }
+
+    /// Pauses the vesting from `from` on. The schedules are resolved to unix timestamps first.
+    pub fn pause_vesting(
+        &mut self,
+        from: U128,
+        current_timestamp: U128,
+        tge_timestamp: Option<u128>,
+    ) {
+        require!(
+            self.paused_since().is_none(),
+            "The vesting is already paused"
+        );
+        require!(from >= current_timestamp, "expected from >= now");
+        let offset = self
+            .schedule_offset(tge_timestamp)
+            .expect("The vesting can't be paused before the TGE timestamp is set");
+        let termination_config = self
+            .termination_config
+            .as_mut()
+            .expect("No termination config");
+        require!(
+            !termination_config.vesting_schedule.is_hidden(),
+            "The hidden vesting schedule has to be revealed before the pause"
+        );
+        if let VestingConditions::Schedule(schedule) = &mut termination_config.vesting_schedule {
+            schedule.shift(offset);
+        }
+        self.schedule.shift(offset);
+        self.anchor = ScheduleAnchor::Absolute;
+        self.paused_intervals
+            .push(PausedInterval { from, to: None });
+    }
+
+    /// Resumes the vesting at `at`. The rest of the lockup schedule and the vesting schedule
+    /// is moved later by the paused duration.
+    pub fn resume_vesting(&mut self, at: U128) {
+        let from = self.paused_since().expect("The vesting is not paused");
+        require!(at >= from, "expected at >= from");
+        let duration = at.0 - from.0;
+        let total_balance = self.schedule.total_balance();
+        expect_valid(self.schedule.pause(from, duration));
+        self.schedule.assert_valid(total_balance);
+        if let Some(TerminationConfig {
+            vesting_schedule: VestingConditions::Schedule(schedule),
+            ..
+        }) = &mut self.termination_config
+        {
+            expect_valid(schedule.pause(from, duration));
+            schedule.assert_valid(total_balance);
+            self.schedule.assert_valid_termination_schedule(schedule);
+        }
+        self.paused_intervals.last_mut().unwrap().to = Some(at);
+    }
+
+    /// The unlocked balance that can be claimed now.
+    pub fn claimable_balance(&self, rounding: Rounding, tge_timestamp: Option<u128>) -> NearToken {
+        if self.is_bad_leaver_pending() {
+            return ZERO_NEAR;
+        }
+        self.unlocked_balance(current_timestamp_sec(), rounding, tge_timestamp)
+            .saturating_sub(self.claimed_balance)
+    }
+
+    pub fn claim(
+        &mut self,
+        index: LockupIndex,
+        claim_amount: NearToken,
+        rounding: Rounding,
+        tge_timestamp: Option<u128>,
+    ) -> LockupClaim {
+        require!(
+            claim_amount == ZERO_NEAR || !self.is_bad_leaver_pending(),
+            format!(
+                "lockup {} can't be claimed while a bad leaver termination is pending",
+                index
+            )
+        );
+        let unlocked_balance =
+            self.unlocked_balance(current_timestamp_sec(), rounding, tge_timestamp);
         let balance_claimed_new = self
             .claimed_balance
             .checked_add(claim_amount)
@@ -62,27 +272,41 @@ impl Lockup {
         }
     }
 
-    pub fn assert_valid(&self, total_balance: NearToken) {
-        assert_eq!(
-            self.claimed_balance, ZERO_NEAR,
+    pub fn validate(&self, total_balance: NearToken) -> Result<(), String> {
+        ensure!(
+            self.claimed_balance == ZERO_NEAR,
             "The initial lockup claimed balance should be 0"
         );
-        self.schedule.assert_valid(total_balance);
+        self.schedule.validate(total_balance)?;
 
         if let Some(termination_config) = &self.termination_config {
+            if let Some(termination_policy) = &termination_config.termination_policy {
+                termination_policy.validate()?;
+            }
             match &termination_config.vesting_schedule {
                 VestingConditions::SameAsLockupSchedule => {
                     // Ok, using lockup schedule.
                 }
-                VestingConditions::Hash(_hash) => {
-                    // Ok, using unknown hash. Can't verify.
-                }
                 VestingConditions::Schedule(schedule) => {
-                    schedule.assert_valid(total_balance);
-                    self.schedule.assert_valid_termination_schedule(schedule);
+                    schedule.validate(total_balance)?;
+                    self.schedule.validate_termination_schedule(schedule)?;
+                }
+                VestingConditions::SignedBy { public_key, .. } => {
+                    ensure!(
+                        public_key.curve_type() == CurveType::ED25519,
+                        "Only ed25519 signing keys are supported"
+                    );
+                }
+                _hidden => {
+                    // Ok, using unknown hash. Can't verify.
                 }
             }
         }
+        Ok(())
+    }
+
+    pub fn assert_valid(&self, total_balance: NearToken) {
+        expect_valid(self.validate(total_balance));
     }
 }
 
@@ -90,41 +314,143 @@ impl Lockup {
 #[derive(Debug, PartialEq, Clone)]
 pub struct LockupCreate {
     pub account_id: AccountId,
-    pub schedule: Schedule,
+    pub schedule: LockupScheduleInput,
     pub vesting_schedule: Option<VestingConditions>,
+    /// What the timestamps of the schedule and the vesting schedule are counted from.
+    #[serde(default, skip_serializing_if = "ScheduleAnchor::is_absolute")]
+    pub anchor: ScheduleAnchor,
+    /// The account ID that receives the refunds of the termination, the funding sender by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beneficiary_id: Option<AccountId>,
+    /// The account ID that can terminate the lockup, the funding sender by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terminator_id: Option<AccountId>,
+    /// The clause applied when the account is terminated as a bad leaver.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination_policy: Option<TerminationPolicy>,
+    /// Where the refunds of the termination go, the beneficiary by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination_disposition: Option<TerminationDisposition>,
+    /// Whether the transferred amount can exceed the total balance of the schedule,
+    /// the excess is returned to the sender. Only a single lockup can opt in.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub refund_excess: bool,
 }
 
 impl LockupCreate {
+    /// Parses the `ft_on_transfer` message, a single lockup or a list of lockups
+    /// whose total balances sum up to the transferred amount.
+    pub fn from_msg(msg: &str) -> serde_json::Result<Vec<Self>> {
+        if msg.trim_start().starts_with('[') {
+            serde_json::from_str(msg)
+        } else {
+            serde_json::from_str(msg).map(|lockup_create| vec![lockup_create])
+        }
+    }
+
     pub fn new_unlocked(account_id: AccountId, total_balance: NearToken) -> Self {
         Self {
             account_id,
-            schedule: Schedule::new_unlocked(total_balance),
+            schedule: Schedule::new_unlocked(total_balance).into(),
             vesting_schedule: None,
+            anchor: ScheduleAnchor::Absolute,
+            beneficiary_id: None,
+            terminator_id: None,
+            termination_policy: None,
+            termination_disposition: None,
+            refund_excess: false,
         }
     }
 
+    /// Relative schedules are resolved against the current block time.
     pub fn into_lockup(&self, payer_id: &AccountId) -> Lockup {
-        let vesting_schedule = self.vesting_schedule.clone();
+        expect_valid(self.try_into_lockup(payer_id, None))
+    }
+
+    /// Same as `into_lockup`, but returns an invalid combination of the options as an error.
+    /// A schedule in shares is resolved against the transferred amount of a single lockup.
+    pub fn try_into_lockup(
+        &self,
+        payer_id: &AccountId,
+        transferred_amount: Option<NearToken>,
+    ) -> Result<Lockup, String> {
+        ensure!(
+            self.termination_policy.is_none() || self.vesting_schedule.is_some(),
+            "termination_policy requires a vesting_schedule"
+        );
+        ensure!(
+            self.beneficiary_id.is_none() || self.vesting_schedule.is_some(),
+            "beneficiary_id requires a vesting_schedule"
+        );
+        ensure!(
+            self.termination_disposition.is_none() || self.vesting_schedule.is_some(),
+            "termination_disposition requires a vesting_schedule"
+        );
+        let mut schedule = self.schedule.resolve(transferred_amount)?;
+        let mut vesting_schedule = self.vesting_schedule.clone();
+        if self.anchor == ScheduleAnchor::Creation {
+            ensure!(
+                !vesting_schedule
+                    .as_ref()
+                    .is_some_and(VestingConditions::is_hidden),
+                "hashed vesting schedule can't be relative to the lockup creation"
+            );
+            let offset = current_timestamp_sec().0;
+            schedule.try_shift(offset)?;
+            if let Some(VestingConditions::Schedule(vesting_schedule)) = &mut vesting_schedule {
+                vesting_schedule.try_shift(offset)?;
+            }
+        }
+        if self.anchor == ScheduleAnchor::Tge {
+            schedule.validate_shift(MAX_TGE_TIMESTAMP)?;
+            if let Some(VestingConditions::Schedule(vesting_schedule)) = &vesting_schedule {
+                vesting_schedule.validate_shift(MAX_TGE_TIMESTAMP)?;
+            }
+        }
         let lockup = Lockup {
             account_id: self.account_id.clone(),
-            schedule: self.schedule.clone(),
+            schedule,
             claimed_balance: ZERO_NEAR,
             termination_config: vesting_schedule.map(|vesting_schedule| TerminationConfig {
-                beneficiary_id: payer_id.clone(),
+                beneficiary_id: self
+                    .beneficiary_id
+                    .clone()
+                    .unwrap_or_else(|| payer_id.clone()),
                 vesting_schedule,
+                terminator_id: self
+                    .terminator_id
+                    .clone()
+                    .unwrap_or_else(|| payer_id.clone()),
+                pending_terminator_id: None,
+                termination_policy: self.termination_policy.clone(),
+                termination_disposition: self.termination_disposition.clone().unwrap_or_default(),
             }),
+            anchor: match self.anchor {
+                ScheduleAnchor::Tge => ScheduleAnchor::Tge,
+                ScheduleAnchor::Absolute | ScheduleAnchor::Creation => ScheduleAnchor::Absolute,
+            },
+            pending_termination: None,
+            paused_intervals: vec![],
         };
-        lockup.assert_valid(lockup.schedule.total_balance());
-        lockup
+        lockup.validate(lockup.schedule.total_balance())?;
+        Ok(lockup)
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use near_sdk::serde_json;
+    use near_sdk::{
+        serde_json,
+        test_utils::{accounts, VMContextBuilder},
+        testing_env,
+    };
 
-    use crate::ONE_YOCTO;
+    use crate::{
+        schedule::{Period, ONE_MONTH_SEC},
+        test_utils::{contract_with_lockups, terminable_lockup_create},
+        ONE_YOCTO,
+    };
 
     use super::*;
 
@@ -138,9 +464,12 @@ mod tests {
             lockup,
             Lockup {
                 account_id,
-                schedule: Schedule::new_unlocked_since(total_balance, timestamp),
+                schedule: Schedule::new_unlocked_since(total_balance, timestamp).into(),
                 claimed_balance: ZERO_NEAR,
-                termination_config: None
+                termination_config: None,
+                anchor: ScheduleAnchor::Absolute,
+                pending_termination: None,
+                paused_intervals: vec![],
             }
         );
         // Bonus check validity.
@@ -153,7 +482,7 @@ mod tests {
         let total_balance = ONE_YOCTO;
         let timestamp = U128(1);
         let mut lockup = Lockup::new_unlocked_since(account_id.clone(), total_balance, timestamp);
-        let claim = lockup.claim(0, ZERO_NEAR);
+        let claim = lockup.claim(0, ZERO_NEAR, Rounding::Floor, None);
         assert_eq!(
             claim,
             LockupClaim {
@@ -171,7 +500,7 @@ mod tests {
         let total_balance = ONE_YOCTO;
         let timestamp = U128(1);
         let mut lockup = Lockup::new_unlocked_since(account_id.clone(), total_balance, timestamp);
-        lockup.claim(0, ONE_YOCTO);
+        lockup.claim(0, ONE_YOCTO, Rounding::Floor, None);
     }
 
     // TODO - test lockup.claim
@@ -187,17 +516,22 @@ mod tests {
     // }
 
     #[test]
-    #[should_panic = "The initial lockup claimed balance should be 0"]
     fn test_assert_valid_fails_initial_claimed() {
         let total_balance = ONE_YOCTO;
         let timestamp = U128(1);
         let lockup = Lockup {
             account_id: "x.near".parse().unwrap(),
-            schedule: Schedule::new_unlocked_since(total_balance, timestamp),
+            schedule: Schedule::new_unlocked_since(total_balance, timestamp).into(),
             claimed_balance: NearToken::from_near(1),
             termination_config: None,
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
         };
-        lockup.assert_valid(total_balance)
+        assert_eq!(
+            lockup.validate(total_balance),
+            Err("The initial lockup claimed balance should be 0".to_string())
+        );
     }
 
     #[test]
@@ -208,12 +542,19 @@ mod tests {
         let schedule = Schedule::new_unlocked_since(total_balance, timestamp);
         let lockup = Lockup {
             account_id: account_id.clone(),
-            schedule: schedule.clone(),
+            schedule: schedule.clone().into(),
             claimed_balance: ZERO_NEAR,
             termination_config: Some(TerminationConfig {
                 beneficiary_id: account_id,
                 vesting_schedule: VestingConditions::Hash(schedule.hash().into()),
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
             }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
         };
         lockup.assert_valid(total_balance)
     }
@@ -226,16 +567,89 @@ mod tests {
         let schedule = Schedule::new_unlocked_since(total_balance, timestamp);
         let lockup = Lockup {
             account_id: account_id.clone(),
-            schedule: schedule.clone(),
+            schedule: schedule.clone().into(),
             claimed_balance: ZERO_NEAR,
             termination_config: Some(TerminationConfig {
                 beneficiary_id: account_id,
-                vesting_schedule: VestingConditions::Schedule(schedule),
+                vesting_schedule: VestingConditions::Schedule(schedule.into()),
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
             }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
         };
         lockup.assert_valid(total_balance)
     }
 
+    #[test]
+    fn test_lockup_tge_relative() {
+        let account_id: AccountId = "x.near".parse().unwrap();
+        let total_balance = NearToken::from_yoctonear(1000);
+        let lockup_create = LockupCreate {
+            account_id,
+            schedule: Schedule::new_cliff_linear(total_balance, 0.into(), 100.into(), 1000.into())
+                .into(),
+            vesting_schedule: None,
+            anchor: ScheduleAnchor::Tge,
+            beneficiary_id: None,
+            terminator_id: None,
+            termination_policy: None,
+            termination_disposition: None,
+            refund_excess: false,
+        };
+        let lockup = lockup_create.into_lockup(&"p.near".parse().unwrap());
+        assert_eq!(lockup.anchor, ScheduleAnchor::Tge);
+        assert_eq!(
+            lockup.schedule,
+            lockup_create.schedule.resolve(None).unwrap()
+        );
+        assert_eq!(lockup.schedule_offset(None), None);
+        assert_eq!(
+            lockup.unlocked_balance(U128(500), Rounding::Floor, None),
+            ZERO_NEAR
+        );
+
+        let tge_timestamp = Some(10_000);
+        assert_eq!(
+            lockup.unlocked_balance(U128(500), Rounding::Floor, tge_timestamp),
+            ZERO_NEAR
+        );
+        assert_eq!(
+            lockup
+                .unlocked_balance(U128(10_500), Rounding::Floor, tge_timestamp)
+                .as_yoctonear(),
+            500
+        );
+        assert_eq!(
+            lockup
+                .resolved_schedule(tge_timestamp)
+                .unwrap()
+                .start_timestamp(),
+            10_000
+        );
+
+        // The schedule must still fit after the latest TGE timestamp.
+        let lockup_create = LockupCreate {
+            schedule: Schedule::new_cliff_linear(
+                total_balance,
+                0.into(),
+                100.into(),
+                (u128::MAX - MAX_TGE_TIMESTAMP + 1).into(),
+            )
+            .into(),
+            ..lockup_create
+        };
+        assert_eq!(
+            lockup_create
+                .try_into_lockup(&"p.near".parse().unwrap(), None)
+                .unwrap_err(),
+            "timestamp overflow"
+        );
+    }
+
     #[test]
     fn test_lockup_create_into_lockup() {
         // env is working on a fresh blockchain starting from time 0
@@ -246,20 +660,33 @@ mod tests {
         let schedule = Schedule::new_unlocked_since(total_balance, timestamp);
         let lockup_create = LockupCreate {
             account_id: account_id.clone(),
-            schedule: schedule.clone(),
+            schedule: schedule.clone().into(),
             vesting_schedule: Some(VestingConditions::SameAsLockupSchedule),
+            anchor: ScheduleAnchor::Absolute,
+            beneficiary_id: None,
+            terminator_id: None,
+            termination_policy: None,
+            termination_disposition: None,
+            refund_excess: false,
         };
         let lockup = lockup_create.into_lockup(&beneficiary_id);
         assert_eq!(
             lockup,
             Lockup {
                 account_id,
-                schedule,
+                schedule: schedule.into(),
                 claimed_balance: ZERO_NEAR,
                 termination_config: Some(TerminationConfig {
-                    beneficiary_id,
-                    vesting_schedule: VestingConditions::SameAsLockupSchedule
-                })
+                    beneficiary_id: beneficiary_id.clone(),
+                    vesting_schedule: VestingConditions::SameAsLockupSchedule,
+                    terminator_id: beneficiary_id,
+                    pending_terminator_id: None,
+                    termination_policy: None,
+                    termination_disposition: TerminationDisposition::Beneficiary,
+                }),
+                anchor: ScheduleAnchor::Absolute,
+                pending_termination: None,
+                paused_intervals: vec![],
             }
         );
     }
@@ -271,8 +698,14 @@ mod tests {
         let timestamp = U128(1);
         let lockup_create = LockupCreate {
             account_id: account_id.clone(),
-            schedule: Schedule::new_unlocked_since(total_balance, timestamp),
+            schedule: Schedule::new_unlocked_since(total_balance, timestamp).into(),
             vesting_schedule: Some(VestingConditions::SameAsLockupSchedule),
+            anchor: ScheduleAnchor::Absolute,
+            beneficiary_id: None,
+            terminator_id: None,
+            termination_policy: None,
+            termination_disposition: None,
+            refund_excess: false,
         };
 
         // Serialize to JSON
@@ -296,4 +729,273 @@ mod tests {
             "BORSH serialization failed"
         );
     }
+
+    #[test]
+    fn test_lockup_create_termination_policy_without_vesting_schedule() {
+        let lockup_create = LockupCreate {
+            termination_policy: Some(TerminationPolicy::ForfeitUnclaimed),
+            ..LockupCreate::new_unlocked("x.near".parse().unwrap(), ONE_YOCTO)
+        };
+        assert_eq!(
+            lockup_create
+                .try_into_lockup(&"p.near".parse().unwrap(), None)
+                .err(),
+            Some("termination_policy requires a vesting_schedule".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lockup_create_relative_to_creation_overflow() {
+        let now = 1_705_312_800;
+        near_sdk::testing_env!(near_sdk::test_utils::VMContextBuilder::new()
+            .block_timestamp(now * 1_000_000_000)
+            .build());
+        let payer_id: AccountId = "p.near".parse().unwrap();
+        let total_balance = NearToken::from_yoctonear(1000);
+        for (schedule, vesting_schedule) in [
+            (
+                ScheduleSpec::Linear {
+                    total_balance,
+                    start: 0,
+                    finish: u128::MAX - 1000,
+                },
+                None,
+            ),
+            // The shift fits, the end of the last period doesn't.
+            (
+                ScheduleSpec::Periodic {
+                    total_balance,
+                    start: u128::MAX - now as u128 - ONE_MONTH_SEC,
+                    period: Period::Monthly,
+                    cliff_periods: 0,
+                    count: 12,
+                },
+                None,
+            ),
+            (
+                Schedule::new_unlocked(total_balance).into(),
+                Some(VestingConditions::Schedule(ScheduleSpec::Linear {
+                    total_balance,
+                    start: 0,
+                    finish: u128::MAX,
+                })),
+            ),
+        ] {
+            let lockup_create = LockupCreate {
+                schedule: schedule.into(),
+                vesting_schedule,
+                anchor: ScheduleAnchor::Creation,
+                ..LockupCreate::new_unlocked("x.near".parse().unwrap(), total_balance)
+            };
+            assert_eq!(
+                lockup_create.try_into_lockup(&payer_id, None).err(),
+                Some("timestamp overflow".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_lockup_create_beneficiary() {
+        let beneficiary_id: AccountId = "b.near".parse().unwrap();
+        let lockup_create = LockupCreate {
+            vesting_schedule: Some(VestingConditions::SameAsLockupSchedule),
+            beneficiary_id: Some(beneficiary_id.clone()),
+            ..LockupCreate::new_unlocked("x.near".parse().unwrap(), ONE_YOCTO)
+        };
+        let termination_config = lockup_create
+            .into_lockup(&"p.near".parse().unwrap())
+            .termination_config
+            .unwrap();
+        assert_eq!(termination_config.beneficiary_id, beneficiary_id);
+        assert_eq!(termination_config.terminator_id.as_str(), "p.near");
+    }
+
+    /// The lockup of 12_000 with a cliff at 6 months, fully unlocked at 12 months.
+    fn accelerated_lockup(vesting_schedule: VestingConditions) -> Lockup {
+        LockupCreate {
+            schedule: Schedule::new_cliff_linear(
+                NearToken::from_yoctonear(12_000),
+                0.into(),
+                (6 * ONE_MONTH_SEC).into(),
+                (12 * ONE_MONTH_SEC).into(),
+            )
+            .into(),
+            vesting_schedule: Some(vesting_schedule),
+            ..terminable_lockup_create()
+        }
+        .into_lockup(&"p.near".parse().unwrap())
+    }
+
+    /// The vesting of 12_000 linearly over 12 months.
+    fn linear_vesting() -> VestingConditions {
+        VestingConditions::Schedule(
+            Schedule::new_cliff_linear(
+                NearToken::from_yoctonear(12_000),
+                0.into(),
+                0.into(),
+                (12 * ONE_MONTH_SEC).into(),
+            )
+            .into(),
+        )
+    }
+
+    #[test]
+    fn test_accelerate_with_vesting_schedule() {
+        let mut lockup = accelerated_lockup(linear_vesting());
+        let now = U128(3 * ONE_MONTH_SEC);
+        lockup.accelerate(Acceleration::Months(3), now, None);
+        assert_eq!(
+            lockup.unlocked_balance(now, Rounding::Floor, None),
+            NearToken::from_yoctonear(6_000)
+        );
+        assert_eq!(
+            lockup.vested_balance(now, Rounding::Floor, None),
+            Some(NearToken::from_yoctonear(6_000))
+        );
+        assert_eq!(lockup.schedule.finish_timestamp(), 9 * ONE_MONTH_SEC);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The hidden vesting schedule has to be revealed before the acceleration"
+    )]
+    fn test_accelerate_hidden_vesting_schedule() {
+        let mut lockup = accelerated_lockup(VestingConditions::Hash([0; 32].into()));
+        lockup.accelerate(Acceleration::Full, U128(ONE_MONTH_SEC), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The schedule can't be accelerated before the TGE timestamp is set")]
+    fn test_accelerate_before_tge() {
+        let mut lockup = accelerated_lockup(VestingConditions::SameAsLockupSchedule);
+        lockup.anchor = ScheduleAnchor::Tge;
+        lockup.accelerate(Acceleration::Full, U128(ONE_MONTH_SEC), None);
+    }
+
+    fn paused_lockup() -> Lockup {
+        let mut lockup = accelerated_lockup(linear_vesting());
+        lockup.pause_vesting(U128(3 * ONE_MONTH_SEC), U128(ONE_MONTH_SEC), None);
+        lockup
+    }
+
+    #[test]
+    fn test_pause_and_resume_vesting() {
+        let mut lockup = paused_lockup();
+        assert_eq!(lockup.paused_since(), Some(U128(3 * ONE_MONTH_SEC)));
+        let later = U128(8 * ONE_MONTH_SEC);
+        assert_eq!(
+            lockup.vested_balance(later, Rounding::Floor, None),
+            Some(NearToken::from_yoctonear(3_000))
+        );
+        assert_eq!(
+            lockup.unlocked_balance(later, Rounding::Floor, None),
+            ZERO_NEAR
+        );
+
+        lockup.resume_vesting(U128(5 * ONE_MONTH_SEC));
+        assert_eq!(
+            lockup.paused_intervals,
+            vec![PausedInterval {
+                from: U128(3 * ONE_MONTH_SEC),
+                to: Some(U128(5 * ONE_MONTH_SEC)),
+            }]
+        );
+        assert_eq!(
+            lockup.vested_balance(later, Rounding::Floor, None),
+            Some(NearToken::from_yoctonear(6_000))
+        );
+        assert_eq!(lockup.schedule.finish_timestamp(), 14 * ONE_MONTH_SEC);
+    }
+
+    #[test]
+    #[should_panic(expected = "The vesting is already paused")]
+    fn test_pause_vesting_twice() {
+        let mut lockup = paused_lockup();
+        lockup.pause_vesting(U128(4 * ONE_MONTH_SEC), U128(ONE_MONTH_SEC), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected at >= from")]
+    fn test_resume_vesting_before_pause() {
+        let mut lockup = paused_lockup();
+        lockup.resume_vesting(U128(2 * ONE_MONTH_SEC));
+    }
+
+    #[test]
+    fn test_terminate_paused_vesting() {
+        let mut lockup = paused_lockup();
+        let outcome = lockup.terminate(0, None, U128(5 * ONE_MONTH_SEC), Rounding::Floor, None);
+        assert_eq!(outcome.0, NearToken::from_yoctonear(9_000));
+        assert_eq!(lockup.paused_since(), None);
+        assert_eq!(
+            lockup.schedule.total_balance(),
+            NearToken::from_yoctonear(3_000)
+        );
+    }
+
+    #[test]
+    fn test_accelerate() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = contract_with_lockups(
+            &mut context,
+            &terminable_lockup_create(),
+            &[accounts(1), accounts(1)],
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(ONE_YOCTO)
+            .block_timestamp(100 * 1_000_000_000)
+            .build());
+        contract.accelerate(vec![0, 1], Acceleration::Full);
+        for index in 0..2 {
+            let lockup = contract.get_lockup(index).unwrap();
+            assert_eq!(lockup.unclaimed_balance, NearToken::from_yoctonear(1000));
+        }
+        let logs = near_sdk::test_utils::get_logs();
+        assert_eq!(logs.len(), 2);
+        assert!(logs[1].contains(r#""event":"ft_lockup_accelerate_lockup""#));
+    }
+
+    #[test]
+    fn test_pause_vesting() {
+        let mut context = VMContextBuilder::new();
+        let mut contract =
+            contract_with_lockups(&mut context, &terminable_lockup_create(), &[accounts(1)]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(ONE_YOCTO)
+            .block_timestamp(100 * 1_000_000_000)
+            .build());
+        contract.pause_vesting(0, None);
+
+        testing_env!(context.block_timestamp(400 * 1_000_000_000).build());
+        let lockup = contract.get_lockup(0).unwrap();
+        assert_eq!(lockup.unclaimed_balance, NearToken::from_yoctonear(100));
+        assert_eq!(lockup.paused_intervals[0].from, U128(100));
+        contract.resume_vesting(0, None);
+        let lockup = contract.get_lockup(0).unwrap();
+        assert_eq!(lockup.unclaimed_balance, NearToken::from_yoctonear(100));
+        assert_eq!(lockup.paused_intervals[0].to, Some(U128(400)));
+        assert_eq!(lockup.schedule.finish_timestamp(), 1300);
+        let logs = near_sdk::test_utils::get_logs();
+        assert!(logs[0].contains(r#""event":"ft_lockup_resume_vesting""#));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected at <= now")]
+    fn test_resume_vesting_in_the_future() {
+        let mut context = VMContextBuilder::new();
+        let mut contract =
+            contract_with_lockups(&mut context, &terminable_lockup_create(), &[accounts(1)]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(ONE_YOCTO)
+            .block_timestamp(100 * 1_000_000_000)
+            .build());
+        contract.pause_vesting(0, None);
+        contract.resume_vesting(0, Some(U128(200)));
+    }
 }
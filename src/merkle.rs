@@ -0,0 +1,147 @@
+use crate::schedule::ScheduleSpec;
+use near_sdk::{env, require, AccountId, CryptoHash};
+
+/// Domain separation of the leaves and the inner nodes,
+/// so an inner node can't be presented as a leaf.
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+fn sha256(value: &[u8]) -> CryptoHash {
+    let value_hash = env::sha256(value);
+    let mut res = CryptoHash::default();
+    res.copy_from_slice(&value_hash);
+    res
+}
+
+/// The leaf of a vesting schedule of the given account.
+/// It's bound to the account, so a proof can't be reused for another lockup of the cohort.
+pub fn merkle_leaf(account_id: &AccountId, schedule: &ScheduleSpec) -> CryptoHash {
+    let mut value = vec![LEAF_PREFIX];
+    value.extend(borsh::to_vec(account_id).unwrap());
+    value.extend(schedule.hash());
+    sha256(&value)
+}
+
+/// The pairs are hashed in sorted order, so the proof doesn't need the sides of the siblings.
+fn merkle_node(left: &CryptoHash, right: &CryptoHash) -> CryptoHash {
+    let (left, right) = if left <= right {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    sha256(&[&[NODE_PREFIX], left.as_slice(), right.as_slice()].concat())
+}
+
+/// Hashes every level into the next one. The last odd node is moved up as is.
+fn next_level(level: &[CryptoHash]) -> Vec<CryptoHash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => merkle_node(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// The root of a tree of a single leaf is the leaf hashed once more with the node prefix,
+/// so a root is never a bare leaf. It's still as easy to guess as the unsalted leaf,
+/// a schedule that isn't shared with a cohort should be hidden behind a salted hash instead.
+fn merkle_single_root(leaf: &CryptoHash) -> CryptoHash {
+    sha256(&[&[NODE_PREFIX], leaf.as_slice()].concat())
+}
+
+pub fn merkle_root(leaves: &[CryptoHash]) -> CryptoHash {
+    require!(!leaves.is_empty(), "at least one leaf is required");
+    if let [leaf] = leaves {
+        return merkle_single_root(leaf);
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// The siblings of the leaf at the given index from the bottom up.
+pub fn merkle_proof(leaves: &[CryptoHash], index: usize) -> Vec<CryptoHash> {
+    require!(index < leaves.len(), "leaf index out of bounds");
+    let mut proof = vec![];
+    let mut level = leaves.to_vec();
+    let mut index = index;
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+/// Only the single leaf of a tree has an empty proof.
+pub fn verify_merkle_proof(leaf: CryptoHash, proof: &[CryptoHash], root: &CryptoHash) -> bool {
+    if proof.is_empty() {
+        return merkle_single_root(&leaf) == *root;
+    }
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| merkle_node(&node, sibling))
+        == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::Schedule;
+    use near_sdk::NearToken;
+
+    fn leaves(count: u128) -> Vec<CryptoHash> {
+        (1..=count)
+            .map(|amount| {
+                let schedule: ScheduleSpec =
+                    Schedule::new_unlocked(NearToken::from_yoctonear(amount)).into();
+                merkle_leaf(&"x.near".parse().unwrap(), &schedule)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merkle_proofs() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let root = merkle_root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_proof(&leaves, index);
+                assert!(verify_merkle_proof(*leaf, &proof, &root));
+                assert!(
+                    !verify_merkle_proof(leaves[(index + 1) % leaves.len()], &proof, &root)
+                        || count == 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_leaf_is_bound_to_account() {
+        let schedule: ScheduleSpec = Schedule::new_unlocked(NearToken::from_yoctonear(1)).into();
+        assert_ne!(
+            merkle_leaf(&"x.near".parse().unwrap(), &schedule),
+            merkle_leaf(&"y.near".parse().unwrap(), &schedule)
+        );
+        assert_ne!(
+            merkle_leaf(&"x.near".parse().unwrap(), &schedule),
+            schedule.hash()
+        );
+    }
+
+    #[test]
+    fn test_merkle_root_single_leaf() {
+        let leaves = leaves(1);
+        let root = merkle_root(&leaves);
+        assert_ne!(root, leaves[0]);
+        assert!(merkle_proof(&leaves, 0).is_empty());
+        assert!(verify_merkle_proof(leaves[0], &[], &root));
+        assert!(!verify_merkle_proof(leaves[0], &[], &leaves[0]));
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{
+    lockup::{Lockup, LockupIndex},
+    schedule::{Checkpoint, Interpolation, Schedule, ScheduleAnchor},
+    termination::{TerminationConfig, TerminationDisposition, VestingConditions},
+    util::Rounding,
+    Contract, ContractExt, StorageKey,
+};
+use near_sdk::{
+    collections::{LookupMap, UnorderedMap, UnorderedSet, Vector},
+    env,
+    json_types::Base58CryptoHash,
+    near, require, AccountId, NearToken,
+};
+
+/// The range of the lockups `migrate_lockups` hasn't converted yet. The lockups created
+/// after `migrate` are past the end and already in the current layout.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrationProgress {
+    pub next_index: u64,
+    pub end_index: u64,
+}
+
+/// The layouts of the state before the per-lockup terminators.
+#[near(serializers = [borsh])]
+struct LegacyCheckpoint {
+    timestamp: u128,
+    balance: NearToken,
+}
+
+#[near(serializers = [borsh])]
+struct LegacySchedule(Vec<LegacyCheckpoint>);
+
+#[near(serializers = [borsh])]
+enum LegacyVestingConditions {
+    SameAsLockupSchedule,
+    Hash(Base58CryptoHash),
+    Schedule(LegacySchedule),
+}
+
+#[near(serializers = [borsh])]
+struct LegacyTerminationConfig {
+    beneficiary_id: AccountId,
+    vesting_schedule: LegacyVestingConditions,
+}
+
+#[near(serializers = [borsh])]
+struct LegacyLockup {
+    account_id: AccountId,
+    schedule: LegacySchedule,
+    claimed_balance: NearToken,
+    termination_config: Option<LegacyTerminationConfig>,
+}
+
+/// The lockups are read from the raw elements, the vector itself has the same layout.
+#[near(serializers = [borsh])]
+struct LegacyContract {
+    token_id: AccountId,
+    lockups: Vector<Lockup>,
+    account_lockups: LookupMap<AccountId, UnorderedSet<LockupIndex>>,
+    deposit_allowlist: UnorderedSet<AccountId>,
+}
+
+impl From<LegacySchedule> for Schedule {
+    fn from(schedule: LegacySchedule) -> Self {
+        Self(
+            schedule
+                .0
+                .into_iter()
+                .map(|checkpoint| Checkpoint {
+                    timestamp: checkpoint.timestamp,
+                    balance: checkpoint.balance,
+                    interpolation: Interpolation::Linear,
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<LegacyLockup> for Lockup {
+    /// The beneficiary funded the lockup, so it becomes the terminator.
+    fn from(lockup: LegacyLockup) -> Self {
+        Self {
+            account_id: lockup.account_id,
+            schedule: Schedule::from(lockup.schedule).into(),
+            claimed_balance: lockup.claimed_balance,
+            termination_config: lockup.termination_config.map(|termination_config| {
+                TerminationConfig {
+                    terminator_id: termination_config.beneficiary_id.clone(),
+                    beneficiary_id: termination_config.beneficiary_id,
+                    vesting_schedule: match termination_config.vesting_schedule {
+                        LegacyVestingConditions::SameAsLockupSchedule => {
+                            VestingConditions::SameAsLockupSchedule
+                        }
+                        LegacyVestingConditions::Hash(hash) => VestingConditions::Hash(hash),
+                        LegacyVestingConditions::Schedule(schedule) => {
+                            VestingConditions::Schedule(Schedule::from(schedule).into())
+                        }
+                    },
+                    pending_terminator_id: None,
+                    termination_policy: None,
+                    termination_disposition: TerminationDisposition::Beneficiary,
+                }
+            }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
+        }
+    }
+}
+
+impl MigrationProgress {
+    pub fn contains(&self, lockup_index: LockupIndex) -> bool {
+        (self.next_index..self.end_index).contains(&lockup_index)
+    }
+}
+
+impl Contract {
+    /// Whether the lockup is still in the legacy layout, see `migrate_lockups`.
+    pub(crate) fn is_unmigrated(&self, lockup_index: LockupIndex) -> bool {
+        self.migration
+            .as_ref()
+            .is_some_and(|migration| migration.contains(lockup_index))
+    }
+}
+
+#[near]
+impl Contract {
+    /// Converts the state deployed before the per-lockup terminators. Only the contract
+    /// itself is converted, so it fits into a single call whatever the number of lockups.
+    /// The lockups have to be converted with `migrate_lockups` before they can be used.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let LegacyContract {
+            token_id,
+            lockups,
+            account_lockups,
+            deposit_allowlist,
+        } = env::state_read().expect("No state to migrate");
+        let migration = Some(MigrationProgress {
+            next_index: 0,
+            end_index: lockups.len(),
+        })
+        .filter(|migration| migration.next_index < migration.end_index);
+        Self {
+            token_id,
+            lockups,
+            account_lockups,
+            deposit_allowlist,
+            rounding: Rounding::Floor,
+            tge_timestamp: None,
+            templates: UnorderedMap::new(StorageKey::Templates),
+            migration,
+        }
+    }
+
+    /// Converts up to `limit` lockups from where the previous call has stopped.
+    /// Returns the number of the lockups left to convert.
+    #[private]
+    pub fn migrate_lockups(&mut self, limit: u64) -> u64 {
+        let Some(mut migration) = self.migration.take() else {
+            return 0;
+        };
+        require!(limit > 0, "limit must be positive");
+        let end_index = std::cmp::min(
+            migration.end_index,
+            migration.next_index.saturating_add(limit),
+        );
+        for index in migration.next_index..end_index {
+            let lockup: LegacyLockup = borsh::from_slice(&self.lockups.get_raw(index).unwrap())
+                .expect("Invalid legacy lockup");
+            self.lockups
+                .replace_raw(index, &borsh::to_vec(&Lockup::from(lockup)).unwrap());
+        }
+        migration.next_index = end_index;
+        let num_left = migration.end_index - migration.next_index;
+        if num_left > 0 {
+            self.migration = Some(migration);
+        }
+        num_left
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::{test_utils::VMContextBuilder, testing_env};
+
+    fn legacy_checkpoints() -> LegacySchedule {
+        LegacySchedule(vec![
+            LegacyCheckpoint {
+                timestamp: 0,
+                balance: NearToken::from_yoctonear(0),
+            },
+            LegacyCheckpoint {
+                timestamp: 100,
+                balance: NearToken::from_yoctonear(1000),
+            },
+        ])
+    }
+
+    /// Writes the legacy state with two lockups of `x.near`, the second one terminable
+    /// by `owner_id`.
+    fn write_legacy_state(owner_id: &AccountId) -> VMContextBuilder {
+        let contract_id: AccountId = "lockup.near".parse().unwrap();
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(contract_id.clone())
+            .predecessor_account_id(contract_id)
+            .build());
+        let account_id: AccountId = "x.near".parse().unwrap();
+        let mut lockups = Vector::<Lockup>::new(StorageKey::Lockups);
+        let mut account_lockups = LookupMap::new(StorageKey::AccountLockups);
+        let mut indices = UnorderedSet::new(StorageKey::AccountLockups);
+        for vesting_schedule in [
+            None,
+            Some(LegacyVestingConditions::Schedule(legacy_checkpoints())),
+        ] {
+            let lockup = LegacyLockup {
+                account_id: account_id.clone(),
+                schedule: legacy_checkpoints(),
+                claimed_balance: NearToken::from_yoctonear(10),
+                termination_config: vesting_schedule.map(|vesting_schedule| {
+                    LegacyTerminationConfig {
+                        beneficiary_id: owner_id.clone(),
+                        vesting_schedule,
+                    }
+                }),
+            };
+            // The vector elements are written raw in the legacy layout.
+            lockups.push(&Lockup::new_unlocked_since(
+                account_id.clone(),
+                NearToken::from_yoctonear(1),
+                1.into(),
+            ));
+            lockups.replace_raw(lockups.len() - 1, &borsh::to_vec(&lockup).unwrap());
+            indices.insert(&(lockups.len() - 1));
+        }
+        account_lockups.insert(&account_id, &indices);
+        env::state_write(&LegacyContract {
+            token_id: "token.near".parse().unwrap(),
+            lockups,
+            account_lockups,
+            deposit_allowlist: UnorderedSet::new(StorageKey::DepositAllowlist),
+        });
+        context
+    }
+
+    #[test]
+    fn test_migrate() {
+        let owner_id: AccountId = "owner.near".parse().unwrap();
+        write_legacy_state(&owner_id);
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.token_id.as_str(), "token.near");
+        assert_eq!(contract.lockups.len(), 2);
+        assert_eq!(
+            contract.migration,
+            Some(MigrationProgress {
+                next_index: 0,
+                end_index: 2,
+            })
+        );
+        // A lockup created during the migration is already in the current layout.
+        let new_lockup = Lockup::new_unlocked_since(
+            "y.near".parse().unwrap(),
+            NearToken::from_yoctonear(5),
+            1.into(),
+        );
+        contract.internal_add_lockup(&new_lockup);
+
+        // The lockups are converted in pages.
+        assert_eq!(contract.migrate_lockups(1), 1);
+        assert_eq!(
+            contract
+                .lockups
+                .get(0)
+                .unwrap()
+                .claimed_balance
+                .as_yoctonear(),
+            10
+        );
+        assert_eq!(contract.migrate_lockups(10), 0);
+        assert_eq!(contract.migration, None);
+        assert_eq!(contract.migrate_lockups(10), 0);
+        assert_eq!(contract.lockups.get(2).unwrap(), new_lockup);
+
+        let lockup = contract.lockups.get(0).unwrap();
+        assert_eq!(lockup.claimed_balance.as_yoctonear(), 10);
+        assert_eq!(
+            lockup.schedule.to_schedule(),
+            Schedule::from(legacy_checkpoints())
+        );
+        assert!(lockup.termination_config.is_none());
+        let termination_config = contract.lockups.get(1).unwrap().termination_config.unwrap();
+        assert_eq!(termination_config.terminator_id, owner_id);
+        assert_eq!(termination_config.beneficiary_id, owner_id);
+        assert_eq!(
+            termination_config.vesting_schedule,
+            VestingConditions::Schedule(Schedule::from(legacy_checkpoints()).into())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "lockup is not migrated yet")]
+    fn test_claim_before_migrate_lockups() {
+        let mut context = write_legacy_state(&"owner.near".parse().unwrap());
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.migrate_lockups(1), 1);
+        assert!(!contract.is_unmigrated(0));
+        assert!(contract.is_unmigrated(1));
+
+        testing_env!(context
+            .predecessor_account_id("x.near".parse().unwrap())
+            .build());
+        contract.claim(None);
+    }
+}
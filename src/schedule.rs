@@ -1,5 +1,75 @@
-use crate::util::ZERO_NEAR;
-use near_sdk::{env, json_types::U128, near, require, CryptoHash, NearToken};
+use crate::util::{
+    civil_from_days, days_from_civil, days_in_month, ensure, expect_valid, mul_div, Rounding,
+    ZERO_NEAR,
+};
+use near_sdk::{
+    env,
+    json_types::U128,
+    near, require,
+    serde::{
+        de::{self, MapAccess, SeqAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    },
+    CryptoHash, NearToken,
+};
+use std::fmt;
+
+pub const ONE_DAY_SEC: u128 = 24 * 60 * 60;
+/// A twelfth of a 365-day year. See `Schedule::new_calendar_monthly` for calendar months.
+pub const ONE_MONTH_SEC: u128 = 365 * ONE_DAY_SEC / 12;
+pub const ONE_QUARTER_SEC: u128 = 3 * ONE_MONTH_SEC;
+/// The denominator of the graded schedule portions.
+pub const BASIS_POINTS: u128 = 10_000;
+/// The denominator of the finest `ShareUnit`.
+pub const PARTS_PER_BILLION: u128 = 1_000_000_000;
+/// The most steps of a periodic, graded or calendar monthly schedule, a century of
+/// monthly steps. Keeps the expanded schedule within the gas of a single call.
+pub const MAX_PERIODS: u32 = 1200;
+/// The latest TGE timestamp. TGE-relative schedules are checked to fit after this shift
+/// on creation, so they can be resolved whenever the TGE timestamp is set.
+pub const MAX_TGE_TIMESTAMP: u128 = u64::MAX as u128;
+
+/// How the balance changes between two consecutive checkpoints.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// The previous balance is held until the checkpoint is reached.
+    StepAtEnd,
+    /// The checkpoint balance is unlocked right after the previous checkpoint.
+    StepAtStart,
+    /// Back-loaded ease-in curve, the unlocked part grows with the square of the passed time.
+    Quadratic,
+    /// The rest of a `Quadratic` curve that has started `offset` seconds before the previous
+    /// checkpoint, e.g. the part of a quadratic segment after a pause.
+    QuadraticRest { offset: u128 },
+}
+
+impl Interpolation {
+    pub fn is_linear(&self) -> bool {
+        *self == Interpolation::Linear
+    }
+
+    pub fn is_quadratic(&self) -> bool {
+        matches!(
+            self,
+            Interpolation::Quadratic | Interpolation::QuadraticRest { .. }
+        )
+    }
+
+    /// The interpolation of the rest of a segment cut `elapsed` seconds after its start,
+    /// so the rest follows the same curve.
+    pub fn rest_after(&self, elapsed: u128) -> Self {
+        match self {
+            Interpolation::Quadratic => Interpolation::QuadraticRest { offset: elapsed },
+            Interpolation::QuadraticRest { offset } => Interpolation::QuadraticRest {
+                offset: offset.saturating_add(elapsed),
+            },
+            interpolation => *interpolation,
+        }
+    }
+}
 
 #[near(serializers = [borsh, json])]
 #[derive(Clone, Debug, PartialEq)]
@@ -7,12 +77,925 @@ pub struct Checkpoint {
     /// The unix-timestamp in seconds since the epoch.
     pub timestamp: u128,
     pub balance: NearToken,
+    /// How the balance is interpolated from the previous checkpoint to this one.
+    #[serde(default, skip_serializing_if = "Interpolation::is_linear")]
+    pub interpolation: Interpolation,
+}
+
+impl Checkpoint {
+    /// The unlocked balance at the given timestamp between the previous checkpoint and this one.
+    /// Assumes `previous.timestamp <= timestamp <= self.timestamp`.
+    fn balance_since(
+        &self,
+        previous: &Checkpoint,
+        timestamp: u128,
+        rounding: Rounding,
+    ) -> NearToken {
+        let total_duration = self.timestamp - previous.timestamp;
+        let passed_duration = timestamp - previous.timestamp;
+        let balance_diff = self.balance.as_yoctonear() - previous.balance.as_yoctonear();
+        let unlocked = match self.interpolation {
+            Interpolation::Linear => {
+                mul_div(passed_duration, balance_diff, total_duration, rounding)
+            }
+            Interpolation::StepAtEnd if passed_duration == total_duration => balance_diff,
+            Interpolation::StepAtStart if passed_duration > 0 => balance_diff,
+            Interpolation::StepAtEnd | Interpolation::StepAtStart => 0,
+            Interpolation::Quadratic => match (
+                passed_duration.checked_mul(passed_duration),
+                total_duration.checked_mul(total_duration),
+            ) {
+                (Some(passed_squared), Some(total_squared)) => {
+                    mul_div(balance_diff, passed_squared, total_squared, rounding)
+                }
+                // Durations beyond u64 can't be squared, so the curve is rounded twice.
+                _ => mul_div(
+                    mul_div(balance_diff, passed_duration, total_duration, rounding),
+                    passed_duration,
+                    total_duration,
+                    rounding,
+                ),
+            },
+            // With the curve starting at `-offset`, the passed part of the segment is
+            // `((offset + passed)^2 - offset^2) / ((offset + total)^2 - offset^2)`.
+            Interpolation::QuadraticRest { offset } => {
+                let double_offset = offset.saturating_mul(2);
+                let passed_factor = double_offset.saturating_add(passed_duration);
+                let total_factor = double_offset.saturating_add(total_duration);
+                match (
+                    passed_duration.checked_mul(passed_factor),
+                    total_duration.checked_mul(total_factor),
+                ) {
+                    (Some(passed_area), Some(total_area)) => {
+                        mul_div(balance_diff, passed_area, total_area, rounding)
+                    }
+                    _ => mul_div(
+                        mul_div(balance_diff, passed_duration, total_duration, rounding),
+                        passed_factor,
+                        total_factor,
+                        rounding,
+                    ),
+                }
+            }
+        };
+        NearToken::from_yoctonear(previous.balance.as_yoctonear() + unlocked)
+    }
 }
 
 #[near(serializers = [borsh, json])]
 #[derive(Debug, PartialEq, Clone)]
 pub struct Schedule(pub Vec<Checkpoint>);
 
+#[near(serializers = [borsh, json])]
+#[derive(Debug, PartialEq, Clone)]
+pub enum Period {
+    Monthly,
+    Quarterly,
+    Seconds(u128),
+}
+
+impl Period {
+    pub fn as_sec(&self) -> u128 {
+        match self {
+            Period::Monthly => ONE_MONTH_SEC,
+            Period::Quarterly => ONE_QUARTER_SEC,
+            Period::Seconds(seconds) => *seconds,
+        }
+    }
+}
+
+/// How much earlier the rest of a schedule is unlocked.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Acceleration {
+    /// Everything is unlocked right away.
+    Full,
+    /// The rest of the schedule is moved the given number of `ONE_MONTH_SEC` months earlier.
+    Months(u32),
+}
+
+impl Acceleration {
+    /// `None` for the full acceleration.
+    pub fn as_sec(&self) -> Option<u128> {
+        match self {
+            Acceleration::Full => None,
+            Acceleration::Months(months) => Some(*months as u128 * ONE_MONTH_SEC),
+        }
+    }
+}
+
+/// What the timestamps of a schedule in `LockupCreate` are counted from.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ScheduleAnchor {
+    /// Unix timestamps in seconds.
+    #[default]
+    Absolute,
+    /// Offsets in seconds from the block time the lockup is created at.
+    /// The schedule is resolved to unix timestamps on creation.
+    Creation,
+    /// Offsets in seconds from the TGE timestamp of the contract.
+    /// Nothing is unlocked until the TGE timestamp is set.
+    Tge,
+}
+
+impl ScheduleAnchor {
+    pub fn is_absolute(&self) -> bool {
+        *self == ScheduleAnchor::Absolute
+    }
+}
+
+/// The parts of the total balance the checkpoints of a `ShareSchedule` are given in.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShareUnit {
+    BasisPoints,
+    PartsPerBillion,
+}
+
+impl ShareUnit {
+    pub fn denominator(&self) -> u128 {
+        match self {
+            ShareUnit::BasisPoints => BASIS_POINTS,
+            ShareUnit::PartsPerBillion => PARTS_PER_BILLION,
+        }
+    }
+}
+
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShareCheckpoint {
+    /// The unix-timestamp in seconds since the epoch.
+    pub timestamp: u128,
+    /// The unlocked part of the total balance in the unit of the schedule.
+    pub share: u32,
+    #[serde(default, skip_serializing_if = "Interpolation::is_linear")]
+    pub interpolation: Interpolation,
+}
+
+/// A schedule of the `ft_on_transfer` message in shares of the transferred amount,
+/// it's resolved into a `Schedule` of the actual amount on deposit.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShareSchedule {
+    pub unit: ShareUnit,
+    pub checkpoints: Vec<ShareCheckpoint>,
+}
+
+impl ShareSchedule {
+    /// The rest of the checks apply to the resolved schedule.
+    pub fn validate(&self) -> Result<(), String> {
+        ensure!(
+            self.checkpoints
+                .windows(2)
+                .all(|pair| pair[0].share <= pair[1].share),
+            "shares must be non-decreasing"
+        );
+        ensure!(
+            self.checkpoints
+                .iter()
+                .all(|checkpoint| checkpoint.share as u128 <= self.unit.denominator()),
+            "shares can't exceed the whole transferred amount"
+        );
+        ensure!(
+            self.checkpoints
+                .last()
+                .is_some_and(|checkpoint| checkpoint.share as u128 == self.unit.denominator()),
+            "the last checkpoint has to unlock the whole transferred amount"
+        );
+        Ok(())
+    }
+
+    /// The balances are rounded down, so the dust is unlocked at the final checkpoint.
+    pub fn to_schedule(&self, total_balance: NearToken) -> Schedule {
+        Schedule(
+            self.checkpoints
+                .iter()
+                .map(|checkpoint| Checkpoint {
+                    timestamp: checkpoint.timestamp,
+                    balance: NearToken::from_yoctonear(mul_div(
+                        total_balance.as_yoctonear(),
+                        checkpoint.share as u128,
+                        self.unit.denominator(),
+                        Rounding::Floor,
+                    )),
+                    interpolation: checkpoint.interpolation,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A lockup schedule as it's stored in the contract.
+/// The parametric variants take constant storage and are evaluated directly, they are
+/// equivalent to the checkpoints produced by the matching `Schedule` builder.
+/// Explicit checkpoints are given as a plain list in JSON.
+#[near(serializers = [borsh, json])]
+#[serde(remote = "Self")]
+#[derive(Debug, PartialEq, Clone)]
+pub enum ScheduleSpec {
+    /// Unlocks linearly from `start` to `finish`.
+    Linear {
+        total_balance: NearToken,
+        start: u128,
+        finish: u128,
+    },
+    /// See `Schedule::new_cliff_linear`.
+    CliffLinear {
+        total_balance: NearToken,
+        start: u128,
+        cliff: u128,
+        finish: u128,
+    },
+    /// See `Schedule::new_periodic`.
+    Periodic {
+        total_balance: NearToken,
+        start: u128,
+        period: Period,
+        cliff_periods: u32,
+        count: u32,
+    },
+    /// See `Schedule::new_graded`.
+    Graded {
+        total_balance: NearToken,
+        start: u128,
+        period: Period,
+        portions_bps: Vec<u16>,
+    },
+    /// See `Schedule::new_calendar_monthly`.
+    CalendarMonthly {
+        total_balance: NearToken,
+        start: u128,
+        cliff_months: u32,
+        months: u32,
+        day_of_month: u8,
+    },
+    Explicit(Schedule),
+}
+
+// Explicit schedules keep the plain list of checkpoints format, so the existing messages
+// are still accepted. An untagged variant can't be used, because serde doesn't support u128
+// in untagged enums.
+impl Serialize for ScheduleSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ScheduleSpec::Explicit(schedule) => schedule.serialize(serializer),
+            _ => ScheduleSpec::serialize(self, serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ScheduleSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ScheduleSpecVisitor;
+
+        impl<'de> Visitor<'de> for ScheduleSpecVisitor {
+            type Value = ScheduleSpec;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list of checkpoints or a schedule spec")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Vec::<Checkpoint>::deserialize(de::value::SeqAccessDeserializer::new(seq))
+                    .map(|checkpoints| ScheduleSpec::Explicit(Schedule(checkpoints)))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                ScheduleSpec::deserialize(de::value::MapAccessDeserializer::new(map))
+            }
+        }
+
+        deserializer.deserialize_any(ScheduleSpecVisitor)
+    }
+}
+
+/// The schedule of a `LockupCreate`. A schedule in shares is given as `{"Shares": ShareSchedule}`
+/// and resolved into a `ScheduleSpec` of the transferred amount on deposit.
+#[near(serializers = [borsh])]
+#[derive(Debug, PartialEq, Clone)]
+pub enum LockupScheduleInput {
+    /// The balances are given in tokens.
+    Absolute(ScheduleSpec),
+    Shares(ShareSchedule),
+}
+
+// A `ScheduleSpec` is given as is, so the existing messages are still accepted.
+impl Serialize for LockupScheduleInput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            LockupScheduleInput::Absolute(schedule) => schedule.serialize(serializer),
+            LockupScheduleInput::Shares(share_schedule) => serializer.serialize_newtype_variant(
+                "LockupScheduleInput",
+                1,
+                "Shares",
+                share_schedule,
+            ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LockupScheduleInput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LockupScheduleInputVisitor;
+
+        impl<'de> Visitor<'de> for LockupScheduleInputVisitor {
+            type Value = LockupScheduleInput;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a schedule spec or a schedule in shares")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                <ScheduleSpec as Deserialize>::deserialize(de::value::SeqAccessDeserializer::new(
+                    seq,
+                ))
+                .map(LockupScheduleInput::Absolute)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let Some(variant) = map.next_key::<String>()? else {
+                    return Err(de::Error::invalid_length(0, &self));
+                };
+                if variant == "Shares" {
+                    return map.next_value().map(LockupScheduleInput::Shares);
+                }
+                let map = PeekedKeyMap::new(variant, map);
+                <ScheduleSpec as Deserialize>::deserialize(de::value::MapAccessDeserializer::new(
+                    map,
+                ))
+                .map(LockupScheduleInput::Absolute)
+            }
+        }
+
+        deserializer.deserialize_any(LockupScheduleInputVisitor)
+    }
+}
+
+/// A map whose first key is already read, it's handed out again before the rest of the map.
+pub(crate) struct PeekedKeyMap<A> {
+    key: Option<String>,
+    map: A,
+}
+
+impl<A> PeekedKeyMap<A> {
+    pub(crate) fn new(key: String, map: A) -> Self {
+        Self {
+            key: Some(key),
+            map,
+        }
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for PeekedKeyMap<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.key.take() {
+            Some(key) => seed
+                .deserialize(de::value::StringDeserializer::new(key))
+                .map(Some),
+            None => self.map.next_key_seed(seed),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.map.next_value_seed(seed)
+    }
+}
+
+impl LockupScheduleInput {
+    /// Resolves a schedule in shares against the transferred amount, which is only given
+    /// for a single lockup.
+    pub fn resolve(&self, transferred_amount: Option<NearToken>) -> Result<ScheduleSpec, String> {
+        match self {
+            LockupScheduleInput::Absolute(schedule) => Ok(schedule.clone()),
+            LockupScheduleInput::Shares(share_schedule) => {
+                let amount = transferred_amount
+                    .ok_or("a schedule in shares is only supported for a single lockup")?;
+                share_schedule.validate()?;
+                Ok(share_schedule.to_schedule(amount).into())
+            }
+        }
+    }
+}
+
+impl From<ScheduleSpec> for LockupScheduleInput {
+    fn from(schedule: ScheduleSpec) -> Self {
+        LockupScheduleInput::Absolute(schedule)
+    }
+}
+
+impl From<Schedule> for LockupScheduleInput {
+    fn from(schedule: Schedule) -> Self {
+        LockupScheduleInput::Absolute(schedule.into())
+    }
+}
+
+impl ScheduleSpec {
+    /// Expands the spec into the list of checkpoints.
+    pub fn to_schedule(&self) -> Schedule {
+        match self {
+            ScheduleSpec::Linear {
+                total_balance,
+                start,
+                finish,
+            } => Schedule::new_cliff_linear(
+                *total_balance,
+                (*start).into(),
+                (*start).into(),
+                (*finish).into(),
+            ),
+            ScheduleSpec::CliffLinear {
+                total_balance,
+                start,
+                cliff,
+                finish,
+            } => Schedule::new_cliff_linear(
+                *total_balance,
+                (*start).into(),
+                (*cliff).into(),
+                (*finish).into(),
+            ),
+            ScheduleSpec::Periodic {
+                total_balance,
+                start,
+                period,
+                cliff_periods,
+                count,
+            } => Schedule::new_periodic(
+                *total_balance,
+                (*start).into(),
+                period,
+                *cliff_periods,
+                *count,
+            ),
+            ScheduleSpec::Graded {
+                total_balance,
+                start,
+                period,
+                portions_bps,
+            } => Schedule::new_graded(*total_balance, (*start).into(), period, portions_bps),
+            ScheduleSpec::CalendarMonthly {
+                total_balance,
+                start,
+                cliff_months,
+                months,
+                day_of_month,
+            } => Schedule::new_calendar_monthly(
+                *total_balance,
+                (*start).into(),
+                *cliff_months,
+                *months,
+                *day_of_month,
+            ),
+            ScheduleSpec::Explicit(schedule) => schedule.clone(),
+        }
+    }
+
+    /// Validates the parameters of the builders, so `to_schedule` can't panic.
+    pub fn validate_params(&self) -> Result<(), String> {
+        match self {
+            ScheduleSpec::Linear { start, finish, .. } => {
+                validate_cliff_linear(*start, *start, *finish)
+            }
+            ScheduleSpec::CliffLinear {
+                start,
+                cliff,
+                finish,
+                ..
+            } => validate_cliff_linear(*start, *cliff, *finish),
+            ScheduleSpec::Periodic {
+                start,
+                period,
+                cliff_periods,
+                count,
+                ..
+            } => validate_periodic(*start, period.as_sec(), *cliff_periods, *count),
+            ScheduleSpec::Graded {
+                start,
+                period,
+                portions_bps,
+                ..
+            } => validate_graded(*start, period.as_sec(), portions_bps),
+            ScheduleSpec::CalendarMonthly {
+                start,
+                cliff_months,
+                months,
+                day_of_month,
+                ..
+            } => validate_calendar_monthly(*start, *cliff_months, *months, *day_of_month),
+            ScheduleSpec::Explicit(_) => Ok(()),
+        }
+    }
+
+    pub fn validate(&self, total_balance: NearToken) -> Result<(), String> {
+        self.validate_params()?;
+        self.to_schedule().validate(total_balance)
+    }
+
+    pub fn assert_valid(&self, total_balance: NearToken) {
+        expect_valid(self.validate(total_balance));
+    }
+
+    pub fn unlocked_balance(&self, current_timestamp: U128, rounding: Rounding) -> NearToken {
+        let current_timestamp = current_timestamp.0;
+        match self {
+            ScheduleSpec::Linear {
+                total_balance,
+                start,
+                finish,
+            } => linear_balance(*total_balance, *start, *finish, current_timestamp, rounding),
+            ScheduleSpec::CliffLinear {
+                total_balance,
+                start,
+                cliff,
+                finish,
+            } => {
+                if current_timestamp < *cliff {
+                    return ZERO_NEAR;
+                }
+                // Interpolating from the balance unlocked at the cliff,
+                // to round the same way as the expanded schedule.
+                let cliff_balance =
+                    linear_balance(*total_balance, *start, *finish, *cliff, Rounding::Floor);
+                cliff_balance.saturating_add(linear_balance(
+                    total_balance.saturating_sub(cliff_balance),
+                    *cliff,
+                    *finish,
+                    current_timestamp,
+                    rounding,
+                ))
+            }
+            ScheduleSpec::Periodic {
+                total_balance,
+                start,
+                period,
+                cliff_periods,
+                count,
+            } => {
+                let period = period.as_sec();
+                let cliff_periods = std::cmp::max(*cliff_periods, 1) as u128;
+                if current_timestamp < expect_valid(period_timestamp(*start, period, cliff_periods))
+                {
+                    return ZERO_NEAR;
+                }
+                let steps = std::cmp::min((current_timestamp - start) / period, *count as u128);
+                portion(*total_balance, steps, *count as u128)
+            }
+            ScheduleSpec::Graded {
+                total_balance,
+                start,
+                period,
+                portions_bps,
+            } => {
+                if current_timestamp < *start {
+                    return ZERO_NEAR;
+                }
+                let steps = (current_timestamp - start) / period.as_sec();
+                let unlocked_bps = portions_bps
+                    .iter()
+                    .take(std::cmp::min(steps, portions_bps.len() as u128) as usize)
+                    .map(|&bps| bps as u128)
+                    .sum();
+                portion(*total_balance, unlocked_bps, BASIS_POINTS)
+            }
+            ScheduleSpec::CalendarMonthly {
+                total_balance,
+                start,
+                cliff_months,
+                months,
+                day_of_month,
+            } => {
+                let steps = std::cmp::min(
+                    calendar_months_passed(*start, current_timestamp, *day_of_month),
+                    *months as u128,
+                );
+                if steps < std::cmp::max(*cliff_months, 1) as u128 {
+                    return ZERO_NEAR;
+                }
+                portion(*total_balance, steps, *months as u128)
+            }
+            ScheduleSpec::Explicit(schedule) => {
+                schedule.unlocked_balance(current_timestamp.into(), rounding)
+            }
+        }
+    }
+
+    pub fn total_balance(&self) -> NearToken {
+        match self {
+            ScheduleSpec::Linear { total_balance, .. }
+            | ScheduleSpec::CliffLinear { total_balance, .. }
+            | ScheduleSpec::Periodic { total_balance, .. }
+            | ScheduleSpec::Graded { total_balance, .. }
+            | ScheduleSpec::CalendarMonthly { total_balance, .. } => *total_balance,
+            ScheduleSpec::Explicit(schedule) => schedule.total_balance(),
+        }
+    }
+
+    /// The timestamp of the first checkpoint.
+    pub fn start_timestamp(&self) -> u128 {
+        match self {
+            ScheduleSpec::Linear { start, .. }
+            | ScheduleSpec::CliffLinear { start, .. }
+            | ScheduleSpec::Periodic { start, .. }
+            | ScheduleSpec::Graded { start, .. }
+            | ScheduleSpec::CalendarMonthly { start, .. } => *start,
+            ScheduleSpec::Explicit(schedule) => schedule.0.first().unwrap().timestamp,
+        }
+    }
+
+    /// The timestamp of the last checkpoint, when the schedule is fully unlocked.
+    pub fn finish_timestamp(&self) -> u128 {
+        match self {
+            ScheduleSpec::Linear { finish, .. } | ScheduleSpec::CliffLinear { finish, .. } => {
+                *finish
+            }
+            ScheduleSpec::Periodic {
+                start,
+                period,
+                count,
+                ..
+            } => expect_valid(period_timestamp(*start, period.as_sec(), *count as u128)),
+            ScheduleSpec::CalendarMonthly {
+                start,
+                months,
+                day_of_month,
+                ..
+            } => expect_valid(calendar_month_timestamp(
+                *start,
+                *months as u128,
+                *day_of_month,
+            )),
+            ScheduleSpec::Graded {
+                start,
+                period,
+                portions_bps,
+                ..
+            } => {
+                // Trailing zero portions don't add checkpoints.
+                let steps = portions_bps.iter().rposition(|&bps| bps > 0).unwrap() + 1;
+                expect_valid(period_timestamp(*start, period.as_sec(), steps as u128))
+            }
+            ScheduleSpec::Explicit(schedule) => schedule.0.last().unwrap().timestamp,
+        }
+    }
+
+    /// Explicit schedules keep the original hash of the checkpoints for compatibility,
+    /// parametric schedules are hashed in their compact form.
+    pub fn hash(&self) -> CryptoHash {
+        match self {
+            ScheduleSpec::Explicit(schedule) => schedule.hash(),
+            _ => {
+                let value_hash = env::sha256(borsh::to_vec(self).unwrap().as_slice());
+                let mut res = CryptoHash::default();
+                res.copy_from_slice(&value_hash);
+                res
+            }
+        }
+    }
+
+    /// Moves all the timestamps of the schedule `offset` seconds later.
+    pub fn shift(&mut self, offset: u128) {
+        expect_valid(self.try_shift(offset));
+    }
+
+    /// Same as `shift`, but returns an overflow as an error and leaves the schedule unchanged.
+    /// The timestamps derived from the start of a parametric schedule are checked by `validate`.
+    pub fn try_shift(&mut self, offset: u128) -> Result<(), String> {
+        match self {
+            ScheduleSpec::Linear { start, finish, .. } => {
+                let shifted = (
+                    shift_timestamp(*start, offset)?,
+                    shift_timestamp(*finish, offset)?,
+                );
+                (*start, *finish) = shifted;
+            }
+            ScheduleSpec::CliffLinear {
+                start,
+                cliff,
+                finish,
+                ..
+            } => {
+                let shifted = (
+                    shift_timestamp(*start, offset)?,
+                    shift_timestamp(*cliff, offset)?,
+                    shift_timestamp(*finish, offset)?,
+                );
+                (*start, *cliff, *finish) = shifted;
+            }
+            ScheduleSpec::Periodic { start, .. }
+            | ScheduleSpec::Graded { start, .. }
+            | ScheduleSpec::CalendarMonthly { start, .. } => {
+                *start = shift_timestamp(*start, offset)?
+            }
+            ScheduleSpec::Explicit(schedule) => schedule.try_shift(offset)?,
+        }
+        Ok(())
+    }
+
+    /// Checks that the schedule is still valid after it's moved `offset` seconds later.
+    pub fn validate_shift(&self, offset: u128) -> Result<(), String> {
+        let mut shifted = self.clone();
+        shifted.try_shift(offset)?;
+        shifted.validate_params()
+    }
+
+    /// A commitment to the schedule that can't be brute-forced from the known schedule
+    /// templates without the salt. The salt should be at least 32 random bytes.
+    pub fn salted_hash(&self, salt: &[u8]) -> CryptoHash {
+        let value_hash = env::sha256(&[salt, self.hash().as_slice()].concat());
+        let mut res = CryptoHash::default();
+        res.copy_from_slice(&value_hash);
+        res
+    }
+
+    /// See `Schedule::terminate`.
+    /// A terminated schedule is no longer parametric, so it's stored as explicit checkpoints.
+    pub fn terminate(&mut self, new_total_balance: NearToken, finish_timestamp: U128) {
+        let mut schedule = self.to_schedule();
+        schedule.terminate(new_total_balance, finish_timestamp);
+        *self = ScheduleSpec::Explicit(schedule);
+    }
+
+    /// See `Schedule::accelerate`.
+    pub fn accelerate(
+        &mut self,
+        current_timestamp: U128,
+        acceleration: Acceleration,
+    ) -> Result<(), String> {
+        let mut schedule = self.to_schedule();
+        schedule.accelerate(current_timestamp, acceleration)?;
+        *self = ScheduleSpec::Explicit(schedule);
+        Ok(())
+    }
+
+    /// See `Schedule::pause`.
+    pub fn pause(&mut self, timestamp: U128, duration: u128) -> Result<(), String> {
+        let mut schedule = self.to_schedule();
+        schedule.pause(timestamp, duration)?;
+        *self = ScheduleSpec::Explicit(schedule);
+        Ok(())
+    }
+
+    /// See `Schedule::validate_termination_schedule`.
+    pub fn validate_termination_schedule(
+        &self,
+        termination_schedule: &ScheduleSpec,
+    ) -> Result<(), String> {
+        self.to_schedule()
+            .validate_termination_schedule(&termination_schedule.to_schedule())
+    }
+
+    pub fn assert_valid_termination_schedule(&self, termination_schedule: &ScheduleSpec) {
+        expect_valid(self.validate_termination_schedule(termination_schedule));
+    }
+}
+
+impl From<Schedule> for ScheduleSpec {
+    fn from(schedule: Schedule) -> Self {
+        Self::Explicit(schedule)
+    }
+}
+
+/// Linearly interpolates `total_balance` between `start` and `finish`.
+fn linear_balance(
+    total_balance: NearToken,
+    start: u128,
+    finish: u128,
+    current_timestamp: u128,
+    rounding: Rounding,
+) -> NearToken {
+    if current_timestamp <= start {
+        return ZERO_NEAR;
+    }
+    if current_timestamp >= finish {
+        return total_balance;
+    }
+    NearToken::from_yoctonear(mul_div(
+        current_timestamp - start,
+        total_balance.as_yoctonear(),
+        finish - start,
+        rounding,
+    ))
+}
+
+/// Computes `total_balance * numerator / denominator` rounding down.
+/// Assumes `numerator <= denominator`.
+fn portion(total_balance: NearToken, numerator: u128, denominator: u128) -> NearToken {
+    NearToken::from_yoctonear(mul_div(
+        total_balance.as_yoctonear(),
+        numerator,
+        denominator,
+        Rounding::Floor,
+    ))
+}
+
+pub(crate) fn shift_timestamp(timestamp: u128, offset: u128) -> Result<u128, String> {
+    timestamp
+        .checked_add(offset)
+        .ok_or_else(|| "timestamp overflow".to_string())
+}
+
+/// The end of the `step`-th `period` after `start`.
+fn period_timestamp(start: u128, period: u128, step: u128) -> Result<u128, String> {
+    shift_timestamp(
+        start,
+        period
+            .checked_mul(step)
+            .ok_or_else(|| "timestamp overflow".to_string())?,
+    )
+}
+
+/// Midnight UTC of the `day_of_month` in the `months`-th calendar month after the month
+/// of `start`. The day is limited to the last day of shorter months.
+/// Only the conversion back to seconds can overflow for a u32 number of `months`.
+fn calendar_month_timestamp(start: u128, months: u128, day_of_month: u8) -> Result<u128, String> {
+    let (year, month, _) = civil_from_days(start / ONE_DAY_SEC);
+    let month_index = year * 12 + month - 1 + months;
+    let (year, month) = (month_index / 12, month_index % 12 + 1);
+    let day = std::cmp::min(day_of_month as u128, days_in_month(year, month));
+    days_from_civil(year, month, day)
+        .checked_mul(ONE_DAY_SEC)
+        .ok_or_else(|| "timestamp overflow".to_string())
+}
+
+/// The number of monthly unlock dates passed since `start` by `current_timestamp`.
+fn calendar_months_passed(start: u128, current_timestamp: u128, day_of_month: u8) -> u128 {
+    if current_timestamp < start {
+        return 0;
+    }
+    let (start_year, start_month, _) = civil_from_days(start / ONE_DAY_SEC);
+    let (year, month, _) = civil_from_days(current_timestamp / ONE_DAY_SEC);
+    let months = year * 12 + month - (start_year * 12 + start_month);
+    if calendar_month_timestamp(start, months, day_of_month)
+        .is_ok_and(|timestamp| timestamp <= current_timestamp)
+    {
+        months
+    } else {
+        months.saturating_sub(1)
+    }
+}
+
+fn validate_cliff_linear(start: u128, cliff: u128, finish: u128) -> Result<(), String> {
+    ensure!(
+        start <= cliff && cliff <= finish && start < finish,
+        "expected start <= cliff <= finish and start < finish"
+    );
+    Ok(())
+}
+
+fn validate_periodic(
+    start: u128,
+    period: u128,
+    cliff_periods: u32,
+    count: u32,
+) -> Result<(), String> {
+    ensure!(period > 0, "period must be positive");
+    ensure!(count > 0, "count must be positive");
+    ensure!(count <= MAX_PERIODS, "expected count <= 1200");
+    ensure!(cliff_periods <= count, "expected cliff_periods <= count");
+    period_timestamp(start, period, count as u128)?;
+    Ok(())
+}
+
+fn validate_graded(start: u128, period: u128, portions_bps: &[u16]) -> Result<(), String> {
+    ensure!(period > 0, "period must be positive");
+    ensure!(
+        portions_bps.len() <= MAX_PERIODS as usize,
+        "expected at most 1200 portions"
+    );
+    ensure!(
+        portions_bps.iter().map(|&bps| bps as u128).sum::<u128>() == BASIS_POINTS,
+        "portions must add up to 10000 basis points"
+    );
+    period_timestamp(start, period, portions_bps.len() as u128)?;
+    Ok(())
+}
+
+fn validate_calendar_monthly(
+    start: u128,
+    cliff_months: u32,
+    months: u32,
+    day_of_month: u8,
+) -> Result<(), String> {
+    ensure!(months > 0, "months must be positive");
+    ensure!(months <= MAX_PERIODS, "expected months <= 1200");
+    ensure!(cliff_months <= months, "expected cliff_months <= months");
+    ensure!(
+        (1..=31).contains(&day_of_month),
+        "expected day_of_month between 1 and 31"
+    );
+    calendar_month_timestamp(start, months as u128, day_of_month)?;
+    Ok(())
+}
+
 impl Schedule {
     pub fn new_zero_balance_from_to(start_timestamp: U128, finish_timestamp: U128) -> Self {
         require!(finish_timestamp > start_timestamp, "Invariant");
@@ -21,10 +1004,12 @@ impl Schedule {
             Checkpoint {
                 timestamp: start_timestamp.0,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: finish_timestamp.0,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
         ])
     }
@@ -35,40 +1020,193 @@ impl Schedule {
             Checkpoint {
                 timestamp: timestamp.0 - 1,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: timestamp.0,
                 balance: total_balance,
+                interpolation: Interpolation::Linear,
             },
         ])
     }
 
-    pub fn new_unlocked(total_balance: NearToken) -> Self {
-        Self::new_unlocked_since(total_balance, 1.into())
+    pub fn new_unlocked(total_balance: NearToken) -> Self {
+        Self::new_unlocked_since(total_balance, 1.into())
+    }
+
+    /// Nothing is unlocked before the `cliff`. At the `cliff` the balance accrued linearly
+    /// since `start` is unlocked at once, the rest is unlocked linearly until `finish`.
+    pub fn new_cliff_linear(
+        total_balance: NearToken,
+        start: U128,
+        cliff: U128,
+        finish: U128,
+    ) -> Self {
+        expect_valid(validate_cliff_linear(start.0, cliff.0, finish.0));
+        let linear = Self(vec![
+            Checkpoint {
+                timestamp: start.0,
+                balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
+            },
+            Checkpoint {
+                timestamp: finish.0,
+                balance: total_balance,
+                interpolation: Interpolation::Linear,
+            },
+        ]);
+        if cliff.0 == start.0 {
+            return linear;
+        }
+        let mut schedule = Self(vec![linear.0[0].clone()]);
+        schedule.push_step(cliff.0, linear.unlocked_balance(cliff, Rounding::Floor));
+        if finish.0 > cliff.0 {
+            schedule.0.push(linear.0[1].clone());
+        }
+        schedule
+    }
+
+    /// Unlocks `total_balance` in `count` equal steps, one every `period` after `start`.
+    /// The first `cliff_periods` steps are unlocked together at the end of the cliff period.
+    /// Rounding dust is unlocked with the last step.
+    pub fn new_periodic(
+        total_balance: NearToken,
+        start: U128,
+        period: &Period,
+        cliff_periods: u32,
+        count: u32,
+    ) -> Self {
+        let period = period.as_sec();
+        expect_valid(validate_periodic(start.0, period, cliff_periods, count));
+        let mut schedule = Self(vec![Checkpoint {
+            timestamp: start.0,
+            balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
+        }]);
+        for step in std::cmp::max(cliff_periods, 1)..=count {
+            schedule.push_step(
+                expect_valid(period_timestamp(start.0, period, step as u128)),
+                portion(total_balance, step as u128, count as u128),
+            );
+        }
+        schedule
+    }
+
+    /// Unlocks a given portion (in basis points) of `total_balance` at the end of every
+    /// `period` after `start`. The portions must add up to `BASIS_POINTS`.
+    /// Rounding dust is unlocked with the last step.
+    pub fn new_graded(
+        total_balance: NearToken,
+        start: U128,
+        period: &Period,
+        portions_bps: &[u16],
+    ) -> Self {
+        let period = period.as_sec();
+        expect_valid(validate_graded(start.0, period, portions_bps));
+        let mut schedule = Self(vec![Checkpoint {
+            timestamp: start.0,
+            balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
+        }]);
+        let mut accumulated_bps = 0;
+        for (index, &bps) in portions_bps.iter().enumerate() {
+            accumulated_bps += bps as u128;
+            let balance = portion(total_balance, accumulated_bps, BASIS_POINTS);
+            if balance > schedule.total_balance() {
+                schedule.push_step(
+                    expect_valid(period_timestamp(start.0, period, index as u128 + 1)),
+                    balance,
+                );
+            }
+        }
+        schedule
+    }
+
+    /// Unlocks `total_balance` in `months` equal steps at midnight UTC on the `day_of_month`
+    /// of every calendar month after the month of `start`, accounting for leap years.
+    /// Months shorter than `day_of_month` unlock on their last day.
+    /// The first `cliff_months` steps are unlocked together at the end of the cliff.
+    /// Rounding dust is unlocked with the last step.
+    pub fn new_calendar_monthly(
+        total_balance: NearToken,
+        start: U128,
+        cliff_months: u32,
+        months: u32,
+        day_of_month: u8,
+    ) -> Self {
+        expect_valid(validate_calendar_monthly(
+            start.0,
+            cliff_months,
+            months,
+            day_of_month,
+        ));
+        let mut schedule = Self(vec![Checkpoint {
+            timestamp: start.0,
+            balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
+        }]);
+        for step in std::cmp::max(cliff_months, 1)..=months {
+            schedule.push_step(
+                expect_valid(calendar_month_timestamp(
+                    start.0,
+                    step as u128,
+                    day_of_month,
+                )),
+                portion(total_balance, step as u128, months as u128),
+            );
+        }
+        schedule
+    }
+
+    /// Appends a step that unlocks up to `balance` at `timestamp`,
+    /// holding the previous balance until the second before.
+    fn push_step(&mut self, timestamp: u128, balance: NearToken) {
+        let last = self.0.last().unwrap().clone();
+        if last.timestamp + 1 < timestamp {
+            self.0.push(Checkpoint {
+                timestamp: timestamp - 1,
+                balance: last.balance,
+                interpolation: Interpolation::Linear,
+            });
+        }
+        self.0.push(Checkpoint {
+            timestamp,
+            balance,
+            interpolation: Interpolation::Linear,
+        });
     }
 
-    pub fn assert_valid(&self, total_balance: NearToken) {
-        require!(self.0.len() >= 2, "at least two checkpoints are required");
-        assert_eq!(
-            self.0.first().unwrap().balance,
-            ZERO_NEAR,
+    pub fn validate(&self, total_balance: NearToken) -> Result<(), String> {
+        ensure!(self.0.len() >= 2, "at least two checkpoints are required");
+        ensure!(
+            self.0.first().unwrap().balance == ZERO_NEAR,
             "first checkpoint balance must be 0"
         );
+        ensure!(
+            self.0.first().unwrap().interpolation.is_linear(),
+            "first checkpoint can't have an interpolation"
+        );
         for i in 1..self.0.len() {
-            require!(self.0[i - 1].timestamp < self.0[i].timestamp, format!("The timestamp of checkpoint #{} should be less than the timestamp of the next checkpoint", i - 1));
-            require!(self.0[i - 1].balance <= self.0[i].balance, format!("The balance of checkpoint #{} should be not greater than the balance of the next checkpoint", i - 1));
+            ensure!(self.0[i - 1].timestamp < self.0[i].timestamp, format!("The timestamp of checkpoint #{} should be less than the timestamp of the next checkpoint", i - 1));
+            ensure!(self.0[i - 1].balance <= self.0[i].balance, format!("The balance of checkpoint #{} should be not greater than the balance of the next checkpoint", i - 1));
         }
-        require!(
+        ensure!(
             self.total_balance() > ZERO_NEAR,
             "total balance must be positive",
         );
-        require!(
+        ensure!(
             self.total_balance() == total_balance,
             "expected total balance doesn't match transferred balance"
         );
+        Ok(())
+    }
+
+    pub fn assert_valid(&self, total_balance: NearToken) {
+        expect_valid(self.validate(total_balance));
     }
 
-    pub fn unlocked_balance(&self, current_timestamp: U128) -> NearToken {
+    /// The balances at the checkpoints are exact, the `rounding` only applies in between them.
+    pub fn unlocked_balance(&self, current_timestamp: U128, rounding: Rounding) -> NearToken {
         // Using binary search by time to find the current checkpoint.
         let index = match self
             .0
@@ -90,28 +1228,171 @@ impl Schedule {
             // The last checkpoint. Fully unlocked.
             return checkpoint.balance;
         }
-        let next_checkpoint = &self.0[index + 1];
-
-        let total_duration = next_checkpoint.timestamp - checkpoint.timestamp;
-        let passed_duration = current_timestamp.0 - checkpoint.timestamp;
-        let vested = checkpoint.balance.as_yoctonear()
-            + passed_duration
-                * (next_checkpoint.balance.as_yoctonear() - checkpoint.balance.as_yoctonear())
-                / total_duration;
-        NearToken::from_yoctonear(vested)
+        self.0[index + 1].balance_since(checkpoint, current_timestamp.0, rounding)
     }
 
     pub fn total_balance(&self) -> NearToken {
         self.0.last().unwrap().balance
     }
 
+    /// Moves all the checkpoints `offset` seconds later.
+    pub fn shift(&mut self, offset: u128) {
+        expect_valid(self.try_shift(offset));
+    }
+
+    /// Same as `shift`, but returns an overflow as an error and leaves the schedule unchanged.
+    pub fn try_shift(&mut self, offset: u128) -> Result<(), String> {
+        let timestamps = self
+            .0
+            .iter()
+            .map(|checkpoint| shift_timestamp(checkpoint.timestamp, offset))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (checkpoint, timestamp) in self.0.iter_mut().zip(timestamps) {
+            checkpoint.timestamp = timestamp;
+        }
+        Ok(())
+    }
+
+    /// Schedules with linear interpolation only are hashed without the interpolation,
+    /// so the hashes created before the interpolation modes were introduced still match.
     pub fn hash(&self) -> CryptoHash {
-        let value_hash = env::sha256(borsh::to_vec(&self.0).unwrap().as_slice());
+        let value = if self
+            .0
+            .iter()
+            .all(|checkpoint| checkpoint.interpolation.is_linear())
+        {
+            borsh::to_vec(
+                &self
+                    .0
+                    .iter()
+                    .map(|checkpoint| (checkpoint.timestamp, checkpoint.balance))
+                    .collect::<Vec<_>>(),
+            )
+        } else {
+            borsh::to_vec(&self.0)
+        };
+        let value_hash = env::sha256(value.unwrap().as_slice());
         let mut res = CryptoHash::default();
         res.copy_from_slice(&value_hash);
         res
     }
 
+    /// Unlocks the rest of the schedule earlier. From `current_timestamp` on the balance is
+    /// what the original schedule unlocks `acceleration` later, so nothing is ever locked again.
+    /// The past of the schedule is collapsed into the second before `current_timestamp`,
+    /// so the same rewrite of a lockup and a vesting schedule keeps the vesting schedule ahead.
+    /// The segment cut at the accelerated timestamp continues the same curve.
+    pub fn accelerate(
+        &mut self,
+        current_timestamp: U128,
+        acceleration: Acceleration,
+    ) -> Result<(), String> {
+        let current_timestamp = current_timestamp.0;
+        let finish_timestamp = self.0.last().unwrap().timestamp;
+        if current_timestamp >= finish_timestamp || acceleration == Acceleration::Months(0) {
+            return Ok(());
+        }
+        let past_timestamp = current_timestamp
+            .checked_sub(1)
+            .ok_or("The schedule can't be accelerated at timestamp 0")?;
+        let accelerated_timestamp = acceleration
+            .as_sec()
+            .map(|offset| current_timestamp.saturating_add(offset))
+            .filter(|&timestamp| timestamp < finish_timestamp);
+        let balance = match accelerated_timestamp {
+            Some(timestamp) => self.unlocked_balance(timestamp.into(), Rounding::Floor),
+            None => self.total_balance(),
+        };
+        let mut checkpoints = vec![
+            Checkpoint {
+                timestamp: past_timestamp,
+                balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
+            },
+            Checkpoint {
+                timestamp: current_timestamp,
+                balance,
+                interpolation: Interpolation::Linear,
+            },
+        ];
+        if let Some(accelerated_timestamp) = accelerated_timestamp {
+            let offset = accelerated_timestamp - current_timestamp;
+            let index = self
+                .0
+                .partition_point(|checkpoint| checkpoint.timestamp <= accelerated_timestamp);
+            let is_cut = index > 0 && self.0[index - 1].timestamp < accelerated_timestamp;
+            for (i, checkpoint) in self.0[index..].iter().enumerate() {
+                let interpolation = if i == 0 && is_cut {
+                    checkpoint
+                        .interpolation
+                        .rest_after(accelerated_timestamp - self.0[index - 1].timestamp)
+                } else {
+                    checkpoint.interpolation
+                };
+                checkpoints.push(Checkpoint {
+                    timestamp: checkpoint.timestamp - offset,
+                    balance: checkpoint.balance,
+                    interpolation,
+                });
+            }
+        }
+        self.0 = checkpoints;
+        Ok(())
+    }
+
+    /// Holds the balance unlocked at `timestamp` for `duration` seconds,
+    /// the rest of the schedule is moved later by the same duration.
+    /// The segment cut at `timestamp` keeps its interpolation up to the pause and continues
+    /// the same curve after it, so the rest never unlocks more than the moved original.
+    pub fn pause(&mut self, timestamp: U128, duration: u128) -> Result<(), String> {
+        let timestamp = timestamp.0;
+        let finish_timestamp = self.0.last().unwrap().timestamp;
+        if timestamp >= finish_timestamp || duration == 0 {
+            return Ok(());
+        }
+        // Every moved timestamp is at most the moved finish.
+        ensure!(
+            finish_timestamp.checked_add(duration).is_some(),
+            "The pause moves the schedule past the last timestamp"
+        );
+        let index = self
+            .0
+            .partition_point(|checkpoint| checkpoint.timestamp <= timestamp);
+        let mut checkpoints = self.0[..index].to_vec();
+        let is_cut = index > 0 && self.0[index - 1].timestamp < timestamp;
+        if index > 0 {
+            let balance = self.unlocked_balance(timestamp.into(), Rounding::Floor);
+            if is_cut {
+                checkpoints.push(Checkpoint {
+                    timestamp,
+                    balance,
+                    interpolation: self.0[index].interpolation,
+                });
+            }
+            checkpoints.push(Checkpoint {
+                timestamp: timestamp + duration,
+                balance,
+                interpolation: Interpolation::Linear,
+            });
+        }
+        for (i, checkpoint) in self.0[index..].iter().enumerate() {
+            let interpolation = if i == 0 && is_cut {
+                checkpoint
+                    .interpolation
+                    .rest_after(timestamp - self.0[index - 1].timestamp)
+            } else {
+                checkpoint.interpolation
+            };
+            checkpoints.push(Checkpoint {
+                timestamp: checkpoint.timestamp + duration,
+                balance: checkpoint.balance,
+                interpolation,
+            });
+        }
+        self.0 = checkpoints;
+        Ok(())
+    }
+
     /// Terminates the lockup schedule earlier.
     /// Assumes new_total_balance is not greater than the current total balance.
     /// This method is unaware of the vested tokens.
@@ -138,15 +1419,41 @@ impl Schedule {
         while let Some(checkpoint) = self.0.pop() {
             if self.0.last().unwrap().balance < new_total_balance {
                 let prev_checkpoint = self.0.last().unwrap().clone();
-                let timestamp_diff = checkpoint.timestamp - prev_checkpoint.timestamp;
-                let balance_diff =
-                    checkpoint.balance.as_yoctonear() - prev_checkpoint.balance.as_yoctonear();
-                let required_balance_diff =
-                    new_total_balance.as_yoctonear() - prev_checkpoint.balance.as_yoctonear();
-                // Computing the new timestamp rounding up
-                let new_timestamp = prev_checkpoint.timestamp
-                    + ((timestamp_diff * required_balance_diff + (balance_diff - 1))
-                        / balance_diff);
+                let new_timestamp = match checkpoint.interpolation {
+                    Interpolation::Linear => {
+                        let timestamp_diff = checkpoint.timestamp - prev_checkpoint.timestamp;
+                        let balance_diff = checkpoint.balance.as_yoctonear()
+                            - prev_checkpoint.balance.as_yoctonear();
+                        let required_balance_diff = new_total_balance.as_yoctonear()
+                            - prev_checkpoint.balance.as_yoctonear();
+                        // Computing the new timestamp rounding up
+                        prev_checkpoint.timestamp
+                            + mul_div(
+                                timestamp_diff,
+                                required_balance_diff,
+                                balance_diff,
+                                Rounding::Ceil,
+                            )
+                    }
+                    // Steps are cut at the same time, only the balance is reduced.
+                    Interpolation::StepAtEnd | Interpolation::StepAtStart => checkpoint.timestamp,
+                    // Cutting the curve at the first second it reaches the new total balance.
+                    // The same curvature is kept, so the new curve is never ahead of the old one.
+                    Interpolation::Quadratic | Interpolation::QuadraticRest { .. } => {
+                        let (mut low, mut high) = (prev_checkpoint.timestamp, checkpoint.timestamp);
+                        while high - low > 1 {
+                            let middle = low + (high - low) / 2;
+                            if checkpoint.balance_since(&prev_checkpoint, middle, Rounding::Floor)
+                                >= new_total_balance
+                            {
+                                high = middle;
+                            } else {
+                                low = middle;
+                            }
+                        }
+                        high
+                    }
+                };
                 // Ensure this funky math can be cast back to u64:
                 require!(
                     new_timestamp <= u64::MAX as u128,
@@ -155,6 +1462,7 @@ impl Schedule {
                 self.0.push(Checkpoint {
                     timestamp: new_timestamp,
                     balance: new_total_balance,
+                    interpolation: checkpoint.interpolation,
                 });
                 return;
             }
@@ -163,11 +1471,16 @@ impl Schedule {
 
     /// Verifies that this schedule is ahead of the given termination schedule at any point of time.
     /// Assumes they have equal total balance and both schedules are valid.
-    pub fn assert_valid_termination_schedule(&self, termination_schedule: &Schedule) {
+    /// Rounding is monotonic, so comparing the floored balances covers both rounding policies.
+    pub fn validate_termination_schedule(
+        &self,
+        termination_schedule: &Schedule,
+    ) -> Result<(), String> {
         for checkpoint in &self.0 {
-            require!(
+            ensure!(
                 checkpoint.balance
-                    <= termination_schedule.unlocked_balance(checkpoint.timestamp.into()),
+                    <= termination_schedule
+                        .unlocked_balance(checkpoint.timestamp.into(), Rounding::Floor),
                 format!(
                     "The lockup schedule is ahead of the termination schedule at timestamp {}",
                     checkpoint.timestamp
@@ -175,14 +1488,94 @@ impl Schedule {
             );
         }
         for checkpoint in &termination_schedule.0 {
-            require!(
-                checkpoint.balance >= self.unlocked_balance(checkpoint.timestamp.into()),
+            ensure!(
+                checkpoint.balance
+                    >= self.unlocked_balance(checkpoint.timestamp.into(), Rounding::Floor),
                 format!(
                     "The termination schedule is ahead of the lockup schedule at timestamp {}",
                     checkpoint.timestamp
                 )
             );
         }
+        // Non-linear segments can overtake each other in between the checkpoints.
+        for timestamp in self.non_linear_timestamps(termination_schedule) {
+            ensure!(
+                self.unlocked_balance(timestamp.into(), Rounding::Floor)
+                    <= termination_schedule.unlocked_balance(timestamp.into(), Rounding::Floor),
+                format!(
+                    "The lockup schedule is ahead of the termination schedule at timestamp {}",
+                    timestamp
+                )
+            );
+        }
+        Ok(())
+    }
+
+    pub fn assert_valid_termination_schedule(&self, termination_schedule: &Schedule) {
+        expect_valid(self.validate_termination_schedule(termination_schedule));
+    }
+
+    /// Returns the timestamps in between the checkpoints where the lockup schedule may
+    /// overtake the termination schedule due to non-linear interpolation.
+    /// Both schedules are monotonic, so right after a step up of the lockup schedule and
+    /// right before a step up of the termination schedule are the only candidates for steps.
+    /// The distance to a quadratic termination curve is convex on the intervals between the
+    /// checkpoints of both schedules, so the closest point is found with a binary search.
+    fn non_linear_timestamps(&self, termination_schedule: &Schedule) -> Vec<u128> {
+        let mut timestamps = vec![];
+        for schedule in [self, termination_schedule] {
+            for segment in schedule.0.windows(2) {
+                let (prev_checkpoint, checkpoint) = (&segment[0], &segment[1]);
+                if checkpoint.timestamp - prev_checkpoint.timestamp < 2 {
+                    continue;
+                }
+                match checkpoint.interpolation {
+                    Interpolation::StepAtStart => timestamps.push(prev_checkpoint.timestamp + 1),
+                    Interpolation::StepAtEnd => timestamps.push(checkpoint.timestamp - 1),
+                    Interpolation::Linear
+                    | Interpolation::Quadratic
+                    | Interpolation::QuadraticRest { .. } => {}
+                }
+            }
+        }
+        for segment in termination_schedule.0.windows(2) {
+            let (prev_checkpoint, checkpoint) = (&segment[0], &segment[1]);
+            if !checkpoint.interpolation.is_quadratic() {
+                continue;
+            }
+            let mut bounds: Vec<u128> = self
+                .0
+                .iter()
+                .map(|checkpoint| checkpoint.timestamp)
+                .filter(|&timestamp| {
+                    prev_checkpoint.timestamp < timestamp && timestamp < checkpoint.timestamp
+                })
+                .collect();
+            bounds.insert(0, prev_checkpoint.timestamp);
+            bounds.push(checkpoint.timestamp);
+            for interval in bounds.windows(2) {
+                let (mut low, mut high) = (interval[0], interval[1]);
+                // Searching for the first second the distance stops decreasing.
+                while low < high {
+                    let middle = low + (high - low) / 2;
+                    let lockup_increase = self
+                        .unlocked_balance((middle + 1).into(), Rounding::Floor)
+                        .saturating_sub(self.unlocked_balance(middle.into(), Rounding::Floor));
+                    let termination_increase = termination_schedule
+                        .unlocked_balance((middle + 1).into(), Rounding::Floor)
+                        .saturating_sub(
+                            termination_schedule.unlocked_balance(middle.into(), Rounding::Floor),
+                        );
+                    if termination_increase >= lockup_increase {
+                        high = middle;
+                    } else {
+                        low = middle + 1;
+                    }
+                }
+                timestamps.push(low);
+            }
+        }
+        timestamps
     }
 }
 #[cfg(test)]
@@ -190,6 +1583,7 @@ mod tests {
     use super::*;
 
     const ONE_NEAR: NearToken = NearToken::from_near(1);
+    const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
 
     #[test]
     fn test_new_happy_paths() {
@@ -200,11 +1594,13 @@ mod tests {
             vec![
                 Checkpoint {
                     timestamp: 1,
-                    balance: ZERO_NEAR
+                    balance: ZERO_NEAR,
+                    interpolation: Interpolation::Linear,
                 },
                 Checkpoint {
                     timestamp: 2,
-                    balance: ZERO_NEAR
+                    balance: ZERO_NEAR,
+                    interpolation: Interpolation::Linear,
                 }
             ]
         );
@@ -217,11 +1613,13 @@ mod tests {
             vec![
                 Checkpoint {
                     timestamp: 1,
-                    balance: ZERO_NEAR
+                    balance: ZERO_NEAR,
+                    interpolation: Interpolation::Linear,
                 },
                 Checkpoint {
                     timestamp: 2,
-                    balance: ONE_NEAR
+                    balance: ONE_NEAR,
+                    interpolation: Interpolation::Linear,
                 }
             ]
         );
@@ -234,16 +1632,600 @@ mod tests {
             vec![
                 Checkpoint {
                     timestamp: 0,
-                    balance: ZERO_NEAR
+                    balance: ZERO_NEAR,
+                    interpolation: Interpolation::Linear,
                 },
                 Checkpoint {
                     timestamp: 1,
-                    balance: ONE_NEAR
+                    balance: ONE_NEAR,
+                    interpolation: Interpolation::Linear,
                 }
             ]
         );
     }
 
+    #[test]
+    fn test_new_cliff_linear() {
+        let total_balance = NearToken::from_yoctonear(1_000);
+        let s = Schedule::new_cliff_linear(total_balance, 100.into(), 200.into(), 500.into());
+        s.assert_valid(total_balance);
+        assert_eq!(s.unlocked_balance(100.into(), Rounding::Floor), ZERO_NEAR);
+        assert_eq!(s.unlocked_balance(199.into(), Rounding::Floor), ZERO_NEAR);
+        assert_eq!(
+            s.unlocked_balance(200.into(), Rounding::Floor),
+            NearToken::from_yoctonear(250)
+        );
+        assert_eq!(
+            s.unlocked_balance(350.into(), Rounding::Floor),
+            NearToken::from_yoctonear(625)
+        );
+        assert_eq!(
+            s.unlocked_balance(500.into(), Rounding::Floor),
+            total_balance
+        );
+
+        // No cliff is a plain linear schedule.
+        let s = Schedule::new_cliff_linear(total_balance, 100.into(), 100.into(), 500.into());
+        s.assert_valid(total_balance);
+        assert_eq!(s.0.len(), 2);
+
+        // Cliff at the finish unlocks everything at once.
+        let s = Schedule::new_cliff_linear(total_balance, 100.into(), 500.into(), 500.into());
+        s.assert_valid(total_balance);
+        assert_eq!(s.unlocked_balance(499.into(), Rounding::Floor), ZERO_NEAR);
+        assert_eq!(
+            s.unlocked_balance(500.into(), Rounding::Floor),
+            total_balance
+        );
+    }
+
+    #[test]
+    fn test_new_cliff_linear_fails_cliff_after_finish() {
+        assert_eq!(
+            validate_cliff_linear(100, 600, 500),
+            Err("expected start <= cliff <= finish and start < finish".to_string())
+        );
+    }
+
+    #[test]
+    fn test_new_periodic() {
+        // 1-year cliff, then monthly for 3 years. Total is not divisible by 48.
+        let total_balance = NearToken::from_yoctonear(1_000_003);
+        let s = Schedule::new_periodic(total_balance, 0.into(), &Period::Monthly, 12, 48);
+        s.assert_valid(total_balance);
+        assert_eq!(
+            s.unlocked_balance((12 * ONE_MONTH_SEC - 1).into(), Rounding::Floor),
+            ZERO_NEAR
+        );
+        assert_eq!(
+            s.unlocked_balance((12 * ONE_MONTH_SEC).into(), Rounding::Floor),
+            NearToken::from_yoctonear(250_000)
+        );
+        assert_eq!(
+            s.unlocked_balance((13 * ONE_MONTH_SEC - 1).into(), Rounding::Floor),
+            NearToken::from_yoctonear(250_000)
+        );
+        assert_eq!(
+            s.unlocked_balance((13 * ONE_MONTH_SEC).into(), Rounding::Floor),
+            NearToken::from_yoctonear(270_834)
+        );
+        assert_eq!(
+            s.unlocked_balance((47 * ONE_MONTH_SEC).into(), Rounding::Floor),
+            NearToken::from_yoctonear(979_169)
+        );
+        // No dust left at the end.
+        assert_eq!(
+            s.unlocked_balance((48 * ONE_MONTH_SEC).into(), Rounding::Floor),
+            total_balance
+        );
+
+        // One-second periods without a cliff.
+        let s = Schedule::new_periodic(ONE_NEAR, 10.into(), &Period::Seconds(1), 0, 4);
+        s.assert_valid(ONE_NEAR);
+        assert_eq!(s.0.len(), 5);
+        assert_eq!(
+            s.unlocked_balance(12.into(), Rounding::Floor),
+            NearToken::from_yoctonear(ONE_NEAR.as_yoctonear() / 2)
+        );
+    }
+
+    #[test]
+    fn test_new_periodic_fails_long_cliff() {
+        assert_eq!(
+            validate_periodic(0, Period::Quarterly.as_sec(), 5, 4),
+            Err("expected cliff_periods <= count".to_string())
+        );
+    }
+
+    #[test]
+    fn test_schedule_spec_too_many_periods() {
+        let total_balance = NearToken::from_yoctonear(1_000_003);
+        let periodic = |count| ScheduleSpec::Periodic {
+            total_balance,
+            start: 0,
+            period: Period::Seconds(1),
+            cliff_periods: 0,
+            count,
+        };
+        periodic(MAX_PERIODS).assert_valid(total_balance);
+        assert_eq!(periodic(MAX_PERIODS).finish_timestamp(), 1200);
+        assert_eq!(
+            periodic(u32::MAX).validate(total_balance),
+            Err("expected count <= 1200".to_string())
+        );
+        assert_eq!(
+            ScheduleSpec::CalendarMonthly {
+                total_balance,
+                start: 0,
+                cliff_months: 0,
+                months: u32::MAX,
+                day_of_month: 1,
+            }
+            .validate(total_balance),
+            Err("expected months <= 1200".to_string())
+        );
+        let mut portions_bps = vec![0; MAX_PERIODS as usize];
+        portions_bps.push(10_000);
+        assert_eq!(
+            ScheduleSpec::Graded {
+                total_balance,
+                start: 0,
+                period: Period::Seconds(1),
+                portions_bps,
+            }
+            .validate(total_balance),
+            Err("expected at most 1200 portions".to_string())
+        );
+    }
+
+    #[test]
+    fn test_new_graded() {
+        let total_balance = NearToken::from_yoctonear(999);
+        let period = Period::Seconds(100);
+        let s = Schedule::new_graded(total_balance, 0.into(), &period, &[1000, 2000, 3000, 4000]);
+        s.assert_valid(total_balance);
+        assert_eq!(s.unlocked_balance(99.into(), Rounding::Floor), ZERO_NEAR);
+        assert_eq!(
+            s.unlocked_balance(100.into(), Rounding::Floor),
+            NearToken::from_yoctonear(99)
+        );
+        assert_eq!(
+            s.unlocked_balance(200.into(), Rounding::Floor),
+            NearToken::from_yoctonear(299)
+        );
+        assert_eq!(
+            s.unlocked_balance(300.into(), Rounding::Floor),
+            NearToken::from_yoctonear(599)
+        );
+        assert_eq!(
+            s.unlocked_balance(400.into(), Rounding::Floor),
+            total_balance
+        );
+
+        // Empty tranches are skipped.
+        let s = Schedule::new_graded(total_balance, 0.into(), &period, &[0, 10_000, 0]);
+        s.assert_valid(total_balance);
+        assert_eq!(s.unlocked_balance(199.into(), Rounding::Floor), ZERO_NEAR);
+        assert_eq!(
+            s.unlocked_balance(200.into(), Rounding::Floor),
+            total_balance
+        );
+    }
+
+    #[test]
+    fn test_new_graded_fails_portions() {
+        assert_eq!(
+            validate_graded(0, Period::Monthly.as_sec(), &[5000, 4000]),
+            Err("portions must add up to 10000 basis points".to_string())
+        );
+    }
+
+    #[test]
+    fn test_new_calendar_monthly() {
+        let total_balance = NearToken::from_yoctonear(4_000_001);
+        // 2024-01-15 10:00 UTC
+        let start = 1_705_312_800;
+        let s = Schedule::new_calendar_monthly(total_balance, start.into(), 2, 4, 31);
+        s.assert_valid(total_balance);
+        let timestamps: Vec<u128> = s.0.iter().map(|checkpoint| checkpoint.timestamp).collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                start,
+                // 2024-03-31, the cliff
+                1_711_843_200 - 1,
+                1_711_843_200,
+                // 2024-04-30
+                1_714_435_200 - 1,
+                1_714_435_200,
+                // 2024-05-31
+                1_717_113_600 - 1,
+                1_717_113_600,
+            ]
+        );
+        assert_eq!(s.0[2].balance.as_yoctonear(), 2_000_000);
+        assert_eq!(s.0[4].balance.as_yoctonear(), 3_000_000);
+        assert_eq!(s.total_balance(), total_balance);
+
+        // Leap day in 2024, but not in 2025 and 2100.
+        let s = Schedule::new_calendar_monthly(total_balance, start.into(), 0, 1, 29);
+        assert_eq!(s.0.last().unwrap().timestamp, 1_709_164_800);
+        // 2025-01-31
+        let s = Schedule::new_calendar_monthly(total_balance, 1_738_281_600.into(), 0, 1, 29);
+        assert_eq!(s.0.last().unwrap().timestamp, 1_740_700_800);
+        // 2099-12-31
+        let s = Schedule::new_calendar_monthly(total_balance, 4_102_358_400.into(), 0, 2, 30);
+        assert_eq!(s.0.last().unwrap().timestamp, 4_107_456_000);
+
+        // Unlocks on the 1st of every month over a year.
+        // 2023-12-31
+        let s = Schedule::new_calendar_monthly(total_balance, 1_703_980_800.into(), 0, 12, 1);
+        assert_eq!(s.0.len(), 25);
+        // 2024-12-01
+        assert_eq!(s.0.last().unwrap().timestamp, 1_733_011_200);
+    }
+
+    #[test]
+    fn test_new_calendar_monthly_fails_day_of_month() {
+        assert_eq!(
+            validate_calendar_monthly(0, 0, 12, 32),
+            Err("expected day_of_month between 1 and 31".to_string())
+        );
+    }
+
+    #[test]
+    fn test_calendar_monthly_spec_matches_expanded_schedule() {
+        let total_balance = NearToken::from_yoctonear(1_000_003);
+        for (start, day_of_month) in [(1_705_312_800, 31), (1_703_980_800, 1), (1_705_312_800, 15)]
+        {
+            let spec = ScheduleSpec::CalendarMonthly {
+                total_balance,
+                start,
+                cliff_months: 3,
+                months: 30,
+                day_of_month,
+            };
+            let schedule = spec.to_schedule();
+            spec.assert_valid(total_balance);
+            assert_eq!(spec.start_timestamp(), start);
+            assert_eq!(
+                spec.finish_timestamp(),
+                schedule.0.last().unwrap().timestamp
+            );
+            let mut timestamps = vec![0, start - 1, start, start + 1, u64::MAX as u128];
+            for checkpoint in &schedule.0 {
+                timestamps.extend([
+                    checkpoint.timestamp - 1,
+                    checkpoint.timestamp,
+                    checkpoint.timestamp + 1,
+                    checkpoint.timestamp + 10 * ONE_DAY_SEC,
+                ]);
+            }
+            for timestamp in timestamps {
+                assert_eq!(
+                    spec.unlocked_balance(timestamp.into(), Rounding::Floor),
+                    schedule.unlocked_balance(timestamp.into(), Rounding::Floor),
+                    "{:?} at {}",
+                    spec,
+                    timestamp
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_schedule_spec_json() {
+        let schedule = Schedule::new_unlocked(ONE_NEAR);
+        let spec: ScheduleSpec =
+            near_sdk::serde_json::from_str(&near_sdk::serde_json::to_string(&schedule).unwrap())
+                .unwrap();
+        assert_eq!(spec, ScheduleSpec::Explicit(schedule.clone()));
+        assert_eq!(spec.to_schedule(), schedule);
+        assert_eq!(
+            near_sdk::serde_json::to_string(&spec).unwrap(),
+            near_sdk::serde_json::to_string(&schedule).unwrap()
+        );
+
+        let spec: ScheduleSpec = near_sdk::serde_json::from_str(
+            r#"{"Periodic": {"total_balance": "48", "start": 0, "period": "Monthly", "cliff_periods": 12, "count": 48}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            spec.to_schedule(),
+            Schedule::new_periodic(
+                NearToken::from_yoctonear(48),
+                0.into(),
+                &Period::Monthly,
+                12,
+                48
+            )
+        );
+    }
+
+    #[test]
+    fn test_lockup_schedule_input_json() {
+        let schedule = Schedule::new_unlocked(ONE_NEAR);
+        let input: LockupScheduleInput =
+            near_sdk::serde_json::from_str(&near_sdk::serde_json::to_string(&schedule).unwrap())
+                .unwrap();
+        assert_eq!(input, LockupScheduleInput::from(schedule));
+
+        let input: LockupScheduleInput = near_sdk::serde_json::from_str(
+            r#"{"Linear": {"total_balance": "48", "start": 0, "finish": 18446744073709551616}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            input,
+            LockupScheduleInput::Absolute(ScheduleSpec::Linear {
+                total_balance: NearToken::from_yoctonear(48),
+                start: 0,
+                finish: 1 << 64,
+            })
+        );
+
+        let json =
+            r#"{"Shares":{"unit":"BasisPoints","checkpoints":[{"timestamp":100,"share":10000}]}}"#;
+        let input: LockupScheduleInput = near_sdk::serde_json::from_str(json).unwrap();
+        assert_eq!(near_sdk::serde_json::to_string(&input).unwrap(), json);
+        assert_eq!(
+            input.resolve(Some(NearToken::from_yoctonear(7))),
+            Ok(ScheduleSpec::Explicit(Schedule(vec![Checkpoint {
+                timestamp: 100,
+                balance: NearToken::from_yoctonear(7),
+                interpolation: Interpolation::Linear,
+            }])))
+        );
+        assert_eq!(
+            input.resolve(None),
+            Err("a schedule in shares is only supported for a single lockup".to_string())
+        );
+    }
+
+    #[test]
+    fn test_schedule_spec_matches_expanded_schedule() {
+        let total_balance = NearToken::from_yoctonear(1_000_003);
+        let period = Period::Seconds(10);
+        let specs = vec![
+            ScheduleSpec::Linear {
+                total_balance,
+                start: 100,
+                finish: 500,
+            },
+            ScheduleSpec::CliffLinear {
+                total_balance,
+                start: 100,
+                cliff: 200,
+                finish: 500,
+            },
+            ScheduleSpec::Periodic {
+                total_balance,
+                start: 100,
+                period: period.clone(),
+                cliff_periods: 12,
+                count: 40,
+            },
+            ScheduleSpec::Periodic {
+                total_balance,
+                start: 100,
+                period: Period::Seconds(1),
+                cliff_periods: 0,
+                count: 400,
+            },
+            ScheduleSpec::Graded {
+                total_balance,
+                start: 100,
+                period,
+                portions_bps: vec![0, 1000, 2000, 0, 3000, 4000, 0],
+            },
+        ];
+        for spec in specs {
+            let schedule = spec.to_schedule();
+            spec.assert_valid(total_balance);
+            assert_eq!(spec.total_balance(), schedule.total_balance());
+            assert_eq!(
+                spec.start_timestamp(),
+                schedule.0.first().unwrap().timestamp
+            );
+            assert_eq!(
+                spec.finish_timestamp(),
+                schedule.0.last().unwrap().timestamp
+            );
+            for timestamp in 0..600 {
+                for rounding in [Rounding::Floor, Rounding::Ceil] {
+                    assert_eq!(
+                        spec.unlocked_balance(timestamp.into(), rounding),
+                        schedule.unlocked_balance(timestamp.into(), rounding),
+                        "{:?} at {} with {:?}",
+                        spec,
+                        timestamp,
+                        rounding
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_schedule_spec_shift() {
+        let total_balance = NearToken::from_yoctonear(1_000_003);
+        let specs = [
+            ScheduleSpec::CliffLinear {
+                total_balance,
+                start: 0,
+                cliff: 100,
+                finish: 400,
+            },
+            ScheduleSpec::Periodic {
+                total_balance,
+                start: 0,
+                period: Period::Seconds(10),
+                cliff_periods: 12,
+                count: 40,
+            },
+            ScheduleSpec::CalendarMonthly {
+                total_balance,
+                start: 0,
+                cliff_months: 1,
+                months: 3,
+                day_of_month: 1,
+            },
+            Schedule::new_cliff_linear(total_balance, 0.into(), 100.into(), 400.into()).into(),
+        ];
+        // 2024-01-15 10:00 UTC
+        let offset = 1_705_312_800;
+        for spec in specs {
+            let mut shifted = spec.clone();
+            shifted.shift(offset);
+            shifted.assert_valid(total_balance);
+            assert_eq!(shifted.start_timestamp(), offset);
+            if !matches!(spec, ScheduleSpec::CalendarMonthly { .. }) {
+                let mut expected = spec.to_schedule();
+                expected.shift(offset);
+                assert_eq!(shifted.to_schedule(), expected);
+                assert_eq!(shifted.finish_timestamp(), spec.finish_timestamp() + offset);
+            }
+        }
+    }
+
+    #[test]
+    fn test_schedule_spec_timestamp_overflow() {
+        let total_balance = NearToken::from_yoctonear(1_000_003);
+        let start = u128::MAX - ONE_MONTH_SEC;
+        let specs = [
+            ScheduleSpec::Periodic {
+                total_balance,
+                start,
+                period: Period::Monthly,
+                cliff_periods: 0,
+                count: 2,
+            },
+            ScheduleSpec::Periodic {
+                total_balance,
+                start: 0,
+                period: Period::Seconds(u128::MAX / 2),
+                cliff_periods: 0,
+                count: 3,
+            },
+            ScheduleSpec::Graded {
+                total_balance,
+                start,
+                period: Period::Monthly,
+                portions_bps: vec![5000, 5000],
+            },
+            ScheduleSpec::CalendarMonthly {
+                total_balance,
+                start,
+                cliff_months: 0,
+                months: MAX_PERIODS,
+                day_of_month: 31,
+            },
+        ];
+        for spec in specs {
+            assert_eq!(
+                spec.validate(total_balance),
+                Err("timestamp overflow".to_string())
+            );
+        }
+
+        // A failed shift leaves the schedule unchanged.
+        let spec = ScheduleSpec::CliffLinear {
+            total_balance,
+            start: 0,
+            cliff: 100,
+            finish: u128::MAX - 10,
+        };
+        let mut shifted = spec.clone();
+        assert_eq!(
+            shifted.try_shift(100),
+            Err("timestamp overflow".to_string())
+        );
+        assert_eq!(shifted, spec);
+        let schedule = spec.to_schedule();
+        let mut shifted = ScheduleSpec::Explicit(schedule.clone());
+        assert!(shifted.try_shift(11).is_err());
+        assert_eq!(shifted, ScheduleSpec::Explicit(schedule));
+
+        // A valid start only overflows with the offset.
+        let mut spec = ScheduleSpec::Periodic {
+            total_balance,
+            start: u128::MAX - 3 * ONE_MONTH_SEC,
+            period: Period::Monthly,
+            cliff_periods: 0,
+            count: 2,
+        };
+        spec.assert_valid(total_balance);
+        spec.try_shift(2 * ONE_MONTH_SEC).unwrap();
+        assert_eq!(
+            spec.validate(total_balance),
+            Err("timestamp overflow".to_string())
+        );
+    }
+
+    #[test]
+    fn test_schedule_spec_is_compact() {
+        let spec = ScheduleSpec::Periodic {
+            total_balance: ONE_NEAR,
+            start: 0,
+            period: Period::Monthly,
+            cliff_periods: 12,
+            count: 48,
+        };
+        let explicit = ScheduleSpec::Explicit(spec.to_schedule());
+        assert!(borsh::to_vec(&spec).unwrap().len() * 10 < borsh::to_vec(&explicit).unwrap().len());
+    }
+
+    #[test]
+    fn test_schedule_spec_hash() {
+        // Explicit schedules keep the checkpoints hash.
+        let schedule = Schedule::new_unlocked(ONE_NEAR);
+        assert_eq!(ScheduleSpec::from(schedule.clone()).hash(), schedule.hash());
+
+        let spec = ScheduleSpec::Linear {
+            total_balance: ONE_NEAR,
+            start: 0,
+            finish: 1,
+        };
+        assert_eq!(spec.to_schedule(), schedule);
+        assert_ne!(spec.hash(), schedule.hash());
+    }
+
+    #[test]
+    fn test_schedule_spec_salted_hash() {
+        let spec: ScheduleSpec = Schedule::new_unlocked(ONE_NEAR).into();
+        let salt = [7; 32];
+        assert_ne!(spec.salted_hash(&salt), spec.hash());
+        assert_ne!(spec.salted_hash(&salt), spec.salted_hash(&[8; 32]));
+        assert_eq!(spec.salted_hash(&salt), spec.clone().salted_hash(&salt));
+        assert_eq!(spec.salted_hash(&[]), {
+            let mut res = CryptoHash::default();
+            res.copy_from_slice(&env::sha256(&spec.hash()));
+            res
+        });
+    }
+
+    #[test]
+    fn test_schedule_spec_termination() {
+        let mut spec = ScheduleSpec::Periodic {
+            total_balance: NearToken::from_near(4),
+            start: 0,
+            period: Period::Seconds(100),
+            cliff_periods: 1,
+            count: 4,
+        };
+        spec.terminate(NearToken::from_near(2), 200.into());
+        assert!(matches!(spec, ScheduleSpec::Explicit(_)));
+        assert_eq!(spec.total_balance(), NearToken::from_near(2));
+        assert_eq!(spec.unlocked_balance(199.into(), Rounding::Floor), ONE_NEAR);
+        assert_eq!(
+            spec.unlocked_balance(200.into(), Rounding::Floor),
+            NearToken::from_near(2)
+        );
+        assert_eq!(
+            spec.unlocked_balance(400.into(), Rounding::Floor),
+            NearToken::from_near(2)
+        );
+    }
+
     #[test]
     fn test_hash() {
         assert_eq!(
@@ -278,101 +2260,120 @@ mod tests {
             Checkpoint {
                 timestamp: 0,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 10,
                 balance: ONE_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 20,
                 balance: NearToken::from_near(2),
+                interpolation: Interpolation::Linear,
             },
         ]);
         schedule.assert_valid(two_near)
     }
 
     #[test]
-    #[should_panic = "The timestamp of checkpoint #0 should be less than the timestamp of the next checkpoint"]
     fn test_assert_valid_fail_increasing_time() {
         let schedule = Schedule(vec![
             Checkpoint {
                 timestamp: 1,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 0,
                 balance: ONE_NEAR,
+                interpolation: Interpolation::Linear,
             },
         ]);
-        schedule.assert_valid(ONE_NEAR)
+        assert_eq!(schedule.validate(ONE_NEAR), Err("The timestamp of checkpoint #0 should be less than the timestamp of the next checkpoint".to_string()));
     }
 
     #[test]
-    #[should_panic = "The balance of checkpoint #1 should be not greater than the balance of the next checkpoint"]
     fn test_assert_valid_fail_increasing_balance() {
         let schedule = Schedule(vec![
             Checkpoint {
                 timestamp: 0,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 1,
                 balance: NearToken::from_near(2),
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 2,
                 balance: ONE_NEAR,
+                interpolation: Interpolation::Linear,
             },
         ]);
-        schedule.assert_valid(ONE_NEAR)
+        assert_eq!(schedule.validate(ONE_NEAR), Err("The balance of checkpoint #1 should be not greater than the balance of the next checkpoint".to_string()));
     }
 
     #[test]
-    #[should_panic = "at least two checkpoints are required"]
     fn test_assert_valid_fail_num_checkpoints() {
-        Schedule(vec![Checkpoint {
-            timestamp: 0,
-            balance: ZERO_NEAR,
-        }])
-        .assert_valid(ZERO_NEAR)
-    }
-
-    #[test]
-    #[should_panic = "first checkpoint balance must be 0"]
-    fn test_assert_valid_fail_zero_start() {
-        Schedule(vec![
-            Checkpoint {
-                timestamp: 0,
-                balance: ONE_NEAR,
-            },
-            Checkpoint {
-                timestamp: 0,
-                balance: ONE_NEAR,
-            },
-        ])
-        .assert_valid(ZERO_NEAR)
+        assert_eq!(
+            Schedule(vec![Checkpoint {
+                timestamp: 0,
+                balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
+            }])
+            .validate(ZERO_NEAR),
+            Err("at least two checkpoints are required".to_string())
+        );
+    }
+
+    #[test]
+    fn test_assert_valid_fail_zero_start() {
+        assert_eq!(
+            Schedule(vec![
+                Checkpoint {
+                    timestamp: 0,
+                    balance: ONE_NEAR,
+                    interpolation: Interpolation::Linear,
+                },
+                Checkpoint {
+                    timestamp: 0,
+                    balance: ONE_NEAR,
+                    interpolation: Interpolation::Linear,
+                },
+            ])
+            .validate(ZERO_NEAR),
+            Err("first checkpoint balance must be 0".to_string())
+        );
     }
 
     #[test]
-    #[should_panic = "total balance must be positive"]
     fn test_assert_valid_fail_positive_total() {
-        Schedule::new_zero_balance_from_to(1.into(), 2.into()).assert_valid(ZERO_NEAR)
+        assert_eq!(
+            Schedule::new_zero_balance_from_to(1.into(), 2.into()).validate(ZERO_NEAR),
+            Err("total balance must be positive".to_string())
+        );
     }
 
     #[test]
-    #[should_panic = "expected total balance doesn't match transferred balance"]
     fn test_assert_valid_fail_total_balance() {
-        Schedule(vec![
-            Checkpoint {
-                timestamp: 0,
-                balance: ZERO_NEAR,
-            },
-            Checkpoint {
-                timestamp: 1,
-                balance: ONE_NEAR,
-            },
-        ])
-        .assert_valid(ZERO_NEAR)
+        assert_eq!(
+            Schedule(vec![
+                Checkpoint {
+                    timestamp: 0,
+                    balance: ZERO_NEAR,
+                    interpolation: Interpolation::Linear,
+                },
+                Checkpoint {
+                    timestamp: 1,
+                    balance: ONE_NEAR,
+                    interpolation: Interpolation::Linear,
+                },
+            ])
+            .validate(ZERO_NEAR),
+            Err("expected total balance doesn't match transferred balance".to_string())
+        );
     }
 
     #[test]
@@ -384,36 +2385,341 @@ mod tests {
             Checkpoint {
                 timestamp: now - 50,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: now + 50,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
         ]);
         assert_eq!(
-            s.unlocked_balance(75.into()),
+            s.unlocked_balance(75.into(), Rounding::Floor),
             NearToken::from_yoctonear(ONE_NEAR.as_yoctonear() / 2)
         );
-        assert_eq!(s.unlocked_balance(now.into()), ONE_NEAR);
+        assert_eq!(s.unlocked_balance(now.into(), Rounding::Floor), ONE_NEAR);
         // SLightly more complex example.
         let s = Schedule(vec![
             Checkpoint {
                 timestamp: 50,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 100,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 200,
                 balance: NearToken::from_near(4),
+                interpolation: Interpolation::Linear,
             },
         ]);
-        assert_eq!(s.unlocked_balance(50.into()), ZERO_NEAR);
-        assert_eq!(s.unlocked_balance(100.into()), two_near);
-        assert_eq!(s.unlocked_balance(150.into()), NearToken::from_near(3));
-        assert_eq!(s.unlocked_balance(200.into()), NearToken::from_near(4));
+        assert_eq!(s.unlocked_balance(50.into(), Rounding::Floor), ZERO_NEAR);
+        assert_eq!(s.unlocked_balance(100.into(), Rounding::Floor), two_near);
+        assert_eq!(
+            s.unlocked_balance(150.into(), Rounding::Floor),
+            NearToken::from_near(3)
+        );
+        assert_eq!(
+            s.unlocked_balance(200.into(), Rounding::Floor),
+            NearToken::from_near(4)
+        );
+    }
+
+    fn curve(interpolation: Interpolation, finish_balance: NearToken) -> Schedule {
+        Schedule(vec![
+            Checkpoint {
+                timestamp: 100,
+                balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
+            },
+            Checkpoint {
+                timestamp: 200,
+                balance: finish_balance,
+                interpolation,
+            },
+        ])
+    }
+
+    #[test]
+    fn test_unlocked_balance_interpolation() {
+        let total_balance = NearToken::from_yoctonear(10_000);
+        let at = |s: &Schedule, timestamp: u128| {
+            s.unlocked_balance(timestamp.into(), Rounding::Floor)
+                .as_yoctonear()
+        };
+
+        let s = curve(Interpolation::StepAtEnd, total_balance);
+        s.assert_valid(total_balance);
+        assert_eq!(at(&s, 100), 0);
+        assert_eq!(at(&s, 199), 0);
+        assert_eq!(at(&s, 200), 10_000);
+
+        let s = curve(Interpolation::StepAtStart, total_balance);
+        s.assert_valid(total_balance);
+        assert_eq!(at(&s, 100), 0);
+        assert_eq!(at(&s, 101), 10_000);
+        assert_eq!(at(&s, 200), 10_000);
+
+        let s = curve(Interpolation::Quadratic, total_balance);
+        s.assert_valid(total_balance);
+        assert_eq!(at(&s, 100), 0);
+        assert_eq!(at(&s, 110), 100);
+        assert_eq!(at(&s, 150), 2_500);
+        assert_eq!(at(&s, 190), 8_100);
+        assert_eq!(at(&s, 200), 10_000);
+    }
+
+    #[test]
+    fn test_unlocked_balance_rounding() {
+        let total_balance = NearToken::from_yoctonear(10);
+        let at = |s: &Schedule, timestamp: u128, rounding: Rounding| {
+            s.unlocked_balance(timestamp.into(), rounding)
+                .as_yoctonear()
+        };
+        let mut s = curve(Interpolation::Linear, total_balance);
+        s.0[1].timestamp = 103;
+
+        assert_eq!(at(&s, 100, Rounding::Ceil), 0);
+        assert_eq!(at(&s, 101, Rounding::Floor), 3);
+        assert_eq!(at(&s, 101, Rounding::Ceil), 4);
+        assert_eq!(at(&s, 102, Rounding::Floor), 6);
+        assert_eq!(at(&s, 102, Rounding::Ceil), 7);
+        assert_eq!(at(&s, 103, Rounding::Floor), 10);
+
+        s.0[1].interpolation = Interpolation::Quadratic;
+        assert_eq!(at(&s, 101, Rounding::Floor), 1);
+        assert_eq!(at(&s, 101, Rounding::Ceil), 2);
+        assert_eq!(at(&s, 102, Rounding::Floor), 4);
+        assert_eq!(at(&s, 102, Rounding::Ceil), 5);
+        assert_eq!(at(&s, 103, Rounding::Ceil), 10);
+    }
+
+    #[test]
+    fn test_unlocked_balance_extreme_values() {
+        let max = u128::MAX;
+        let max_balance = NearToken::from_yoctonear(max);
+        let at = |s: &Schedule, timestamp: u128, rounding: Rounding| {
+            s.unlocked_balance(timestamp.into(), rounding)
+                .as_yoctonear()
+        };
+
+        // (2^128 - 1) / (2^64 - 1) = 2^64 + 1, so the balance is exact every second.
+        let mut s = curve(Interpolation::Linear, max_balance);
+        s.0[0].timestamp = 1;
+        s.0[1].timestamp = 1 + u64::MAX as u128;
+        for rounding in [Rounding::Floor, Rounding::Ceil] {
+            assert_eq!(at(&s, 2, rounding), (1 << 64) + 1);
+            assert_eq!(at(&s, 1 + (1 << 63), rounding), (1 << 127) + (1 << 63));
+            assert_eq!(at(&s, u64::MAX as u128, rounding), max - (1 << 64) - 1);
+            assert_eq!(at(&s, 1 + u64::MAX as u128, rounding), max);
+        }
+
+        // Timestamps up to u128::MAX, the balance is (t - 1) * max / (max - 1).
+        s.0[1].timestamp = max;
+        assert_eq!(at(&s, 2, Rounding::Floor), 1);
+        assert_eq!(at(&s, 2, Rounding::Ceil), 2);
+        assert_eq!(at(&s, max - 1, Rounding::Floor), max - 2);
+        assert_eq!(at(&s, max - 1, Rounding::Ceil), max - 1);
+        assert_eq!(at(&s, max, Rounding::Floor), max);
+
+        let mut s = curve(Interpolation::Quadratic, max_balance);
+        s.0[0].timestamp = 0;
+        s.0[1].timestamp = 1 << 32;
+        assert_eq!(at(&s, 1 << 31, Rounding::Floor), (1 << 126) - 1);
+        assert_eq!(at(&s, 1 << 31, Rounding::Ceil), 1 << 126);
+        assert_eq!(at(&s, 1, Rounding::Floor), (1 << 64) - 1);
+        assert_eq!(at(&s, 1, Rounding::Ceil), 1 << 64);
+
+        // The squared duration doesn't fit, the curve is still bounded and monotonic.
+        s.0[1].timestamp = max;
+        let mut previous = 0;
+        for timestamp in [1, 1 << 64, 1 << 100, 1 << 126, 1 << 127, max - 1, max] {
+            let floor = at(&s, timestamp, Rounding::Floor);
+            let ceil = at(&s, timestamp, Rounding::Ceil);
+            assert!(previous <= floor && floor <= ceil && ceil <= max);
+            previous = floor;
+        }
+        assert_eq!(at(&s, 1 << 127, Rounding::Floor), 1 << 126);
+        assert_eq!(previous, max);
+
+        let specs = [
+            ScheduleSpec::Linear {
+                total_balance: max_balance,
+                start: 1,
+                finish: 1 + u64::MAX as u128,
+            },
+            ScheduleSpec::CliffLinear {
+                total_balance: max_balance,
+                start: 1,
+                cliff: 1 << 62,
+                finish: 1 + u64::MAX as u128,
+            },
+            ScheduleSpec::Periodic {
+                total_balance: max_balance,
+                start: 1,
+                period: Period::Seconds(1 << 60),
+                cliff_periods: 3,
+                count: 15,
+            },
+            ScheduleSpec::Graded {
+                total_balance: max_balance,
+                start: 1,
+                period: Period::Seconds(1 << 62),
+                portions_bps: vec![1, 3333, 3333, 3333],
+            },
+        ];
+        for spec in specs {
+            let schedule = spec.to_schedule();
+            spec.assert_valid(max_balance);
+            for timestamp in [
+                0,
+                1,
+                2,
+                1 << 61,
+                (1 << 62) + 7,
+                1 << 63,
+                u64::MAX as u128,
+                max,
+            ] {
+                for rounding in [Rounding::Floor, Rounding::Ceil] {
+                    assert_eq!(
+                        spec.unlocked_balance(timestamp.into(), rounding),
+                        schedule.unlocked_balance(timestamp.into(), rounding),
+                        "{:?} at {} with {:?}",
+                        spec,
+                        timestamp,
+                        rounding
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_termination_extreme_values() {
+        let max_balance = NearToken::from_yoctonear(u128::MAX);
+        let mut s = curve(Interpolation::Linear, max_balance);
+        s.0[0].timestamp = 1;
+        s.0[1].timestamp = 1 + u64::MAX as u128;
+
+        let half = NearToken::from_yoctonear((1 << 127) + (1 << 63));
+        let mut terminated = s.clone();
+        terminated.terminate(half, 0.into());
+        assert_eq!(terminated.0.last().unwrap().timestamp, 1 + (1 << 63));
+        assert_eq!(terminated.total_balance(), half);
+
+        // The termination timestamp is rounded up, so the new schedule is never ahead.
+        let mut terminated = s.clone();
+        terminated.terminate(ONE_YOCTO, 0.into());
+        assert_eq!(terminated.0.last().unwrap().timestamp, 2);
+        assert_eq!(
+            terminated.unlocked_balance(2.into(), Rounding::Floor),
+            ONE_YOCTO
+        );
+        for timestamp in [1, 2, 1 << 63, u64::MAX as u128] {
+            assert!(
+                terminated.unlocked_balance(timestamp.into(), Rounding::Ceil)
+                    <= s.unlocked_balance(timestamp.into(), Rounding::Ceil)
+            );
+        }
+    }
+
+    #[test]
+    fn test_assert_valid_fail_first_interpolation() {
+        let mut s = curve(Interpolation::Linear, ONE_NEAR);
+        s.0[0].interpolation = Interpolation::StepAtEnd;
+        assert_eq!(
+            s.validate(ONE_NEAR),
+            Err("first checkpoint can't have an interpolation".to_string())
+        );
+    }
+
+    #[test]
+    fn test_interpolation_json() {
+        let s: Schedule = near_sdk::serde_json::from_str(
+            r#"[{"timestamp": 100, "balance": "0"}, {"timestamp": 200, "balance": "1", "interpolation": "StepAtEnd"}]"#,
+        )
+        .unwrap();
+        assert_eq!(s, curve(Interpolation::StepAtEnd, ONE_YOCTO));
+        // Linear interpolation is omitted.
+        assert_eq!(
+            near_sdk::serde_json::to_string(&s).unwrap(),
+            r#"[{"timestamp":100,"balance":"0"},{"timestamp":200,"balance":"1","interpolation":"StepAtEnd"}]"#
+        );
+        assert_ne!(s.hash(), curve(Interpolation::Linear, ONE_YOCTO).hash());
+        let s = curve(Interpolation::QuadraticRest { offset: 50 }, ONE_YOCTO);
+        assert_eq!(
+            near_sdk::serde_json::to_string(&s).unwrap(),
+            r#"[{"timestamp":100,"balance":"0"},{"timestamp":200,"balance":"1","interpolation":{"QuadraticRest":{"offset":50}}}]"#
+        );
+    }
+
+    #[test]
+    fn test_termination_interpolation() {
+        let total_balance = NearToken::from_yoctonear(10_000);
+        let half = NearToken::from_yoctonear(5_000);
+
+        let mut s = curve(Interpolation::StepAtEnd, total_balance);
+        s.terminate(half, 150.into());
+        assert_eq!(s, curve(Interpolation::StepAtEnd, half));
+
+        let mut s = curve(Interpolation::StepAtStart, total_balance);
+        s.terminate(half, 150.into());
+        assert_eq!(s, curve(Interpolation::StepAtStart, half));
+
+        // The curve is cut as soon as it reaches the new total balance.
+        let original = curve(Interpolation::Quadratic, total_balance);
+        let mut s = original.clone();
+        s.terminate(half, 150.into());
+        s.assert_valid(half);
+        assert_eq!(s.0[1].timestamp, 171);
+        assert_eq!(s.0[1].interpolation, Interpolation::Quadratic);
+        for timestamp in 100..=200 {
+            let balance = s.unlocked_balance(timestamp.into(), Rounding::Floor);
+            assert!(balance <= original.unlocked_balance(timestamp.into(), Rounding::Floor));
+            assert!(balance <= half);
+        }
+    }
+
+    #[test]
+    fn test_valid_termination_schedule_interpolation_passes() {
+        let total_balance = NearToken::from_yoctonear(10_000);
+        // Holding the lockup until the end is never ahead of any vesting curve.
+        let s = curve(Interpolation::StepAtEnd, total_balance);
+        s.assert_valid_termination_schedule(&curve(Interpolation::Quadratic, total_balance));
+        s.assert_valid_termination_schedule(&curve(Interpolation::Linear, total_balance));
+        // A quadratic lockup is behind a linear vesting.
+        curve(Interpolation::Quadratic, total_balance)
+            .assert_valid_termination_schedule(&curve(Interpolation::Linear, total_balance));
+    }
+
+    #[test]
+    fn test_valid_termination_schedule_interpolation_step() {
+        let total_balance = NearToken::from_yoctonear(10_000);
+        assert_eq!(
+            curve(Interpolation::Linear, total_balance)
+                .validate_termination_schedule(&curve(Interpolation::StepAtEnd, total_balance)),
+            Err(
+                "The lockup schedule is ahead of the termination schedule at timestamp 199"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_valid_termination_schedule_interpolation_curve() {
+        let total_balance = NearToken::from_yoctonear(10_000);
+        assert_eq!(
+            curve(Interpolation::Linear, total_balance)
+                .validate_termination_schedule(&curve(Interpolation::Quadratic, total_balance)),
+            Err(
+                "The lockup schedule is ahead of the termination schedule at timestamp 150"
+                    .to_string()
+            )
+        );
     }
 
     #[test]
@@ -424,25 +2730,28 @@ mod tests {
             Checkpoint {
                 timestamp: 50,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 100,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 200,
                 balance: four_near,
+                interpolation: Interpolation::Linear,
             },
         ]);
         s.terminate(ONE_NEAR, 100.into());
-        assert_eq!(s.unlocked_balance(50.into()), ZERO_NEAR);
-        assert_eq!(s.unlocked_balance(100.into()), ONE_NEAR);
-        assert_eq!(s.unlocked_balance(200.into()), ONE_NEAR);
+        assert_eq!(s.unlocked_balance(50.into(), Rounding::Floor), ZERO_NEAR);
+        assert_eq!(s.unlocked_balance(100.into(), Rounding::Floor), ONE_NEAR);
+        assert_eq!(s.unlocked_balance(200.into(), Rounding::Floor), ONE_NEAR);
 
         s.terminate(ZERO_NEAR, 100.into());
-        assert_eq!(s.unlocked_balance(50.into()), ZERO_NEAR);
-        assert_eq!(s.unlocked_balance(100.into()), ZERO_NEAR);
-        assert_eq!(s.unlocked_balance(200.into()), ZERO_NEAR);
+        assert_eq!(s.unlocked_balance(50.into(), Rounding::Floor), ZERO_NEAR);
+        assert_eq!(s.unlocked_balance(100.into(), Rounding::Floor), ZERO_NEAR);
+        assert_eq!(s.unlocked_balance(200.into(), Rounding::Floor), ZERO_NEAR);
 
         s.terminate(ZERO_NEAR, 50.into());
     }
@@ -455,30 +2764,34 @@ mod tests {
             Checkpoint {
                 timestamp: 50,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 100,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 200,
                 balance: four_near,
+                interpolation: Interpolation::Linear,
             },
         ]);
         s.assert_valid_termination_schedule(&Schedule(vec![
             Checkpoint {
                 timestamp: 0,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 200,
                 balance: four_near,
+                interpolation: Interpolation::Linear,
             },
         ]));
     }
 
     #[test]
-    #[should_panic = "The lockup schedule is ahead of the termination schedule at timestamp 200"]
     fn test_valid_termination_schedule_panics() {
         let two_near = NearToken::from_near(2);
         let four_near = NearToken::from_near(4);
@@ -486,31 +2799,42 @@ mod tests {
             Checkpoint {
                 timestamp: 50,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 100,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 200,
                 balance: four_near,
+                interpolation: Interpolation::Linear,
             },
         ]);
-        s.assert_valid_termination_schedule(&Schedule(vec![
+        let result = s.validate_termination_schedule(&Schedule(vec![
             Checkpoint {
                 timestamp: 50,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 100,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
         ]));
+        assert_eq!(
+            result,
+            Err(
+                "The lockup schedule is ahead of the termination schedule at timestamp 200"
+                    .to_string()
+            )
+        );
         s.assert_valid_termination_schedule(&s);
     }
 
     #[test]
-    #[should_panic = "The lockup schedule is ahead of the termination schedule at timestamp 100"]
     fn test_valid_termination_schedule_lockup_ahead_of_termination() {
         let two_near = NearToken::from_near(2);
         let four_near = NearToken::from_near(4);
@@ -518,35 +2842,46 @@ mod tests {
             Checkpoint {
                 timestamp: 0,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 100,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 200,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 300,
                 balance: four_near,
+                interpolation: Interpolation::Linear,
             },
         ]);
         let termination_schedule = Schedule(vec![
             Checkpoint {
                 timestamp: 0,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 300,
                 balance: four_near,
+                interpolation: Interpolation::Linear,
             },
         ]);
-        s.assert_valid_termination_schedule(&termination_schedule);
+        assert_eq!(
+            s.validate_termination_schedule(&termination_schedule),
+            Err(
+                "The lockup schedule is ahead of the termination schedule at timestamp 100"
+                    .to_string()
+            )
+        );
     }
 
     #[test]
-    #[should_panic = "The termination schedule is ahead of the lockup schedule at timestamp 200"]
     fn test_valid_termination_schedule_panics_case_b() {
         let two_near = NearToken::from_near(2);
         let four_near = NearToken::from_near(4);
@@ -554,30 +2889,414 @@ mod tests {
             Checkpoint {
                 timestamp: 0,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 100,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 200,
                 balance: two_near,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 300,
                 balance: four_near,
+                interpolation: Interpolation::Linear,
             },
         ]);
         let s = Schedule(vec![
             Checkpoint {
                 timestamp: 0,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 300,
                 balance: four_near,
+                interpolation: Interpolation::Linear,
+            },
+        ]);
+        assert_eq!(
+            s.validate_termination_schedule(&termination_schedule),
+            Err(
+                "The termination schedule is ahead of the lockup schedule at timestamp 200"
+                    .to_string()
+            )
+        );
+    }
+
+    /// Checks that the accelerated schedule is what the original one unlocks `offset` later
+    /// and never less than the original one, from `current_timestamp` on.
+    fn assert_accelerated(
+        original: &Schedule,
+        accelerated: &Schedule,
+        current_timestamp: u128,
+        offset: u128,
+    ) {
+        accelerated.assert_valid(original.total_balance());
+        let finish = original.0.last().unwrap().timestamp;
+        for timestamp in (current_timestamp..=finish).step_by(ONE_DAY_SEC as usize) {
+            let balance = accelerated.unlocked_balance(timestamp.into(), Rounding::Floor);
+            assert!(balance >= original.unlocked_balance(timestamp.into(), Rounding::Floor));
+            assert!(
+                balance >= original.unlocked_balance((timestamp + offset).into(), Rounding::Floor)
+            );
+        }
+    }
+
+    #[test]
+    fn test_accelerate_linear() {
+        let original = Schedule::new_cliff_linear(
+            NearToken::from_yoctonear(12_000),
+            0.into(),
+            0.into(),
+            (12 * ONE_MONTH_SEC).into(),
+        );
+        let mut schedule = original.clone();
+        let now = 3 * ONE_MONTH_SEC;
+        schedule
+            .accelerate(now.into(), Acceleration::Months(3))
+            .unwrap();
+        assert_eq!(
+            schedule.unlocked_balance(now.into(), Rounding::Floor),
+            NearToken::from_yoctonear(6_000)
+        );
+        assert_eq!(schedule.0.last().unwrap().timestamp, 9 * ONE_MONTH_SEC);
+        assert_accelerated(&original, &schedule, now, 3 * ONE_MONTH_SEC);
+    }
+
+    #[test]
+    fn test_accelerate_full() {
+        let original = Schedule::new_cliff_linear(
+            NearToken::from_yoctonear(12_000),
+            0.into(),
+            (6 * ONE_MONTH_SEC).into(),
+            (12 * ONE_MONTH_SEC).into(),
+        );
+        let mut schedule = original.clone();
+        let now = ONE_MONTH_SEC;
+        schedule.accelerate(now.into(), Acceleration::Full).unwrap();
+        schedule.assert_valid(original.total_balance());
+        assert_eq!(
+            schedule.unlocked_balance(now.into(), Rounding::Floor),
+            original.total_balance()
+        );
+        assert_eq!(schedule.0.last().unwrap().timestamp, now);
+    }
+
+    #[test]
+    fn test_accelerate_before_cliff() {
+        let original = Schedule::new_cliff_linear(
+            NearToken::from_yoctonear(12_000),
+            0.into(),
+            (6 * ONE_MONTH_SEC).into(),
+            (12 * ONE_MONTH_SEC).into(),
+        );
+        let mut schedule = original.clone();
+        let now = ONE_MONTH_SEC;
+        schedule
+            .accelerate(now.into(), Acceleration::Months(2))
+            .unwrap();
+        assert_eq!(
+            schedule.unlocked_balance(now.into(), Rounding::Floor),
+            ZERO_NEAR
+        );
+        assert_eq!(
+            schedule.unlocked_balance((4 * ONE_MONTH_SEC).into(), Rounding::Floor),
+            NearToken::from_yoctonear(6_000)
+        );
+        assert_accelerated(&original, &schedule, now, 2 * ONE_MONTH_SEC);
+    }
+
+    #[test]
+    fn test_accelerate_cut_quadratic_segment() {
+        let original = Schedule(vec![
+            Checkpoint {
+                timestamp: 0,
+                balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
+            },
+            Checkpoint {
+                timestamp: 12 * ONE_MONTH_SEC,
+                balance: NearToken::from_yoctonear(1_000_000),
+                interpolation: Interpolation::Quadratic,
+            },
+        ]);
+        let mut schedule = original.clone();
+        let now = 2 * ONE_MONTH_SEC;
+        schedule
+            .accelerate(now.into(), Acceleration::Months(4))
+            .unwrap();
+        assert_eq!(
+            schedule.0.last().unwrap().interpolation,
+            Interpolation::QuadraticRest {
+                offset: 6 * ONE_MONTH_SEC
+            }
+        );
+        // The rest follows the original curve instead of its chord.
+        for timestamp in (now..=8 * ONE_MONTH_SEC).step_by(ONE_DAY_SEC as usize) {
+            assert_eq!(
+                schedule.unlocked_balance(timestamp.into(), Rounding::Floor),
+                original.unlocked_balance((timestamp + 4 * ONE_MONTH_SEC).into(), Rounding::Floor)
+            );
+        }
+    }
+
+    #[test]
+    fn test_accelerate_at_zero() {
+        let original = Schedule::new_cliff_linear(ONE_NEAR, 0.into(), 0.into(), 100.into());
+        let mut schedule = original.clone();
+        assert_eq!(
+            schedule.accelerate(0.into(), Acceleration::Full),
+            Err("The schedule can't be accelerated at timestamp 0".to_string())
+        );
+        assert_eq!(schedule, original);
+    }
+
+    /// Checks that the paused schedule holds the balance unlocked at `timestamp`
+    /// for `duration` and unlocks the rest `duration` later.
+    fn assert_paused(original: &Schedule, paused: &Schedule, timestamp: u128, duration: u128) {
+        paused.assert_valid(original.total_balance());
+        let balance_at_pause = original.unlocked_balance(timestamp.into(), Rounding::Floor);
+        let finish = original.0.last().unwrap().timestamp;
+        for t in (0..=finish + duration).step_by(ONE_DAY_SEC as usize) {
+            let balance = paused.unlocked_balance(t.into(), Rounding::Floor);
+            if t <= timestamp {
+                // The cut curve ends at the rounded balance at the pause.
+                let original_balance = original.unlocked_balance(t.into(), Rounding::Floor);
+                assert!(balance <= original_balance);
+                assert!(balance.saturating_add(ONE_YOCTO) >= original_balance);
+            } else if t <= timestamp + duration {
+                assert_eq!(balance, balance_at_pause);
+            } else {
+                // Only the rounding of the balance at the pause can hold a yocto back.
+                let moved_balance =
+                    original.unlocked_balance((t - duration).into(), Rounding::Floor);
+                assert!(balance >= balance_at_pause);
+                assert!(
+                    balance <= moved_balance,
+                    "{} > {} at {}",
+                    balance,
+                    moved_balance,
+                    t
+                );
+                assert!(balance.saturating_add(ONE_YOCTO) >= moved_balance);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pause_linear() {
+        let original = Schedule::new_cliff_linear(
+            NearToken::from_yoctonear(12_000),
+            0.into(),
+            0.into(),
+            (12 * ONE_MONTH_SEC).into(),
+        );
+        let mut schedule = original.clone();
+        schedule
+            .pause((3 * ONE_MONTH_SEC).into(), 2 * ONE_MONTH_SEC)
+            .unwrap();
+        assert_eq!(
+            schedule.unlocked_balance((5 * ONE_MONTH_SEC).into(), Rounding::Floor),
+            NearToken::from_yoctonear(3_000)
+        );
+        assert_eq!(
+            schedule.unlocked_balance((8 * ONE_MONTH_SEC).into(), Rounding::Floor),
+            NearToken::from_yoctonear(6_000)
+        );
+        assert_eq!(schedule.0.last().unwrap().timestamp, 14 * ONE_MONTH_SEC);
+        assert_paused(&original, &schedule, 3 * ONE_MONTH_SEC, 2 * ONE_MONTH_SEC);
+    }
+
+    #[test]
+    fn test_pause_before_start() {
+        let original = Schedule::new_cliff_linear(
+            NearToken::from_yoctonear(12_000),
+            ONE_MONTH_SEC.into(),
+            ONE_MONTH_SEC.into(),
+            (12 * ONE_MONTH_SEC).into(),
+        );
+        let mut schedule = original.clone();
+        schedule.pause(0.into(), ONE_MONTH_SEC).unwrap();
+        assert_eq!(
+            schedule.0[0].timestamp,
+            original.0[0].timestamp + ONE_MONTH_SEC
+        );
+        assert_paused(&original, &schedule, 0, ONE_MONTH_SEC);
+    }
+
+    #[test]
+    fn test_pause_cut_quadratic_segment() {
+        let original = Schedule(vec![
+            Checkpoint {
+                timestamp: 0,
+                balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
+            },
+            Checkpoint {
+                timestamp: 12 * ONE_MONTH_SEC,
+                balance: NearToken::from_yoctonear(1_000_000),
+                interpolation: Interpolation::Quadratic,
+            },
+        ]);
+        let mut schedule = original.clone();
+        schedule
+            .pause((6 * ONE_MONTH_SEC).into(), ONE_MONTH_SEC)
+            .unwrap();
+        assert_paused(&original, &schedule, 6 * ONE_MONTH_SEC, ONE_MONTH_SEC);
+        assert_eq!(
+            schedule.0[3].interpolation,
+            Interpolation::QuadraticRest {
+                offset: 6 * ONE_MONTH_SEC
+            }
+        );
+
+        // The rest is still on the curve when the pause doesn't fall on a round balance,
+        // and after a second pause of the rest.
+        let original = Schedule(vec![
+            Checkpoint {
+                timestamp: 0,
+                balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
+            },
+            Checkpoint {
+                timestamp: 12 * ONE_MONTH_SEC,
+                balance: NearToken::from_yoctonear(1_000_003),
+                interpolation: Interpolation::Quadratic,
             },
         ]);
-        s.assert_valid_termination_schedule(&termination_schedule);
+        let mut schedule = original.clone();
+        let pause_timestamp = 5 * ONE_MONTH_SEC + 12_345;
+        schedule
+            .pause(pause_timestamp.into(), ONE_MONTH_SEC)
+            .unwrap();
+        assert_paused(&original, &schedule, pause_timestamp, ONE_MONTH_SEC);
+        let paused_once = schedule.clone();
+        let pause_timestamp = 9 * ONE_MONTH_SEC + 777;
+        schedule
+            .pause(pause_timestamp.into(), 2 * ONE_MONTH_SEC)
+            .unwrap();
+        assert_paused(&paused_once, &schedule, pause_timestamp, 2 * ONE_MONTH_SEC);
+        // Both pauses together never unlock more than the original moved by both durations.
+        for t in
+            (pause_timestamp + 2 * ONE_MONTH_SEC..=15 * ONE_MONTH_SEC).step_by(ONE_DAY_SEC as usize)
+        {
+            assert!(
+                schedule.unlocked_balance(t.into(), Rounding::Floor)
+                    <= original.unlocked_balance((t - 3 * ONE_MONTH_SEC).into(), Rounding::Floor)
+            );
+        }
+    }
+
+    #[test]
+    fn test_pause_finished_schedule() {
+        let original = Schedule::new_unlocked_since(ONE_NEAR, 10.into());
+        let mut schedule = original.clone();
+        schedule.pause(20.into(), 10).unwrap();
+        assert_eq!(schedule, original);
+    }
+
+    #[test]
+    fn test_pause_timestamp_overflow() {
+        let original =
+            Schedule::new_cliff_linear(ONE_NEAR, 0.into(), 0.into(), (u128::MAX - 10).into());
+        let mut schedule = original.clone();
+        assert_eq!(
+            schedule.pause(100.into(), 11),
+            Err("The pause moves the schedule past the last timestamp".to_string())
+        );
+        assert_eq!(schedule, original);
+        schedule.pause(100.into(), 10).unwrap();
+        assert_eq!(schedule.0.last().unwrap().timestamp, u128::MAX);
+    }
+
+    #[test]
+    fn test_accelerate_finished_schedule() {
+        let original = Schedule::new_unlocked_since(ONE_NEAR, 10.into());
+        let mut schedule = original.clone();
+        schedule.accelerate(20.into(), Acceleration::Full).unwrap();
+        assert_eq!(schedule, original);
+    }
+
+    #[test]
+    fn test_share_schedule_to_schedule() {
+        let share_schedule = ShareSchedule {
+            unit: ShareUnit::PartsPerBillion,
+            checkpoints: vec![
+                ShareCheckpoint {
+                    timestamp: 0,
+                    share: 0,
+                    interpolation: Interpolation::Linear,
+                },
+                ShareCheckpoint {
+                    timestamp: 100,
+                    share: 333_333_333,
+                    interpolation: Interpolation::StepAtEnd,
+                },
+                ShareCheckpoint {
+                    timestamp: 200,
+                    share: 1_000_000_000,
+                    interpolation: Interpolation::Linear,
+                },
+            ],
+        };
+        share_schedule.validate().unwrap();
+        let schedule = share_schedule.to_schedule(NearToken::from_yoctonear(100));
+        assert_eq!(
+            schedule
+                .0
+                .iter()
+                .map(|checkpoint| checkpoint.balance.as_yoctonear())
+                .collect::<Vec<_>>(),
+            vec![0, 33, 100]
+        );
+        assert_eq!(schedule.0[1].interpolation, Interpolation::StepAtEnd);
+        schedule.assert_valid(NearToken::from_yoctonear(100));
+    }
+
+    #[test]
+    fn test_share_schedule_validate() {
+        let share_schedule = ShareSchedule {
+            unit: ShareUnit::BasisPoints,
+            checkpoints: vec![ShareCheckpoint {
+                timestamp: 100,
+                share: 9_999,
+                interpolation: Interpolation::Linear,
+            }],
+        };
+        assert_eq!(
+            share_schedule.validate(),
+            Err("the last checkpoint has to unlock the whole transferred amount".to_string())
+        );
+
+        let share_schedule = |shares: &[u32]| ShareSchedule {
+            unit: ShareUnit::BasisPoints,
+            checkpoints: shares
+                .iter()
+                .enumerate()
+                .map(|(index, &share)| ShareCheckpoint {
+                    timestamp: index as u128 * 100,
+                    share,
+                    interpolation: Interpolation::Linear,
+                })
+                .collect(),
+        };
+        assert_eq!(share_schedule(&[0, 6000, 10000]).validate(), Ok(()));
+        assert_eq!(
+            share_schedule(&[0, 6000, 5000, 10000]).validate(),
+            Err("shares must be non-decreasing".to_string())
+        );
+        assert_eq!(
+            share_schedule(&[0, 20000, 10000]).validate(),
+            Err("shares must be non-decreasing".to_string())
+        );
+        assert_eq!(
+            share_schedule(&[0, 10000, 20000]).validate(),
+            Err("shares can't exceed the whole transferred amount".to_string())
+        );
     }
 }
@@ -0,0 +1,180 @@
+use crate::{
+    lockup::LockupCreate,
+    schedule::{shift_timestamp, Checkpoint, Interpolation, Schedule, BASIS_POINTS},
+    util::{ensure, mul_div, Rounding},
+};
+use near_sdk::{near, serde_json, AccountId, NearToken};
+
+pub type TemplateId = String;
+
+/// A checkpoint of a template, relative to the lockup start and in basis points of the total.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemplateCheckpoint {
+    /// The seconds since the start of the lockup.
+    pub offset: u128,
+    /// The unlocked part of the total balance at the checkpoint.
+    pub basis_points: u16,
+    #[serde(default, skip_serializing_if = "Interpolation::is_linear")]
+    pub interpolation: Interpolation,
+}
+
+/// A named vesting shape, expanded against the transferred amount when a lockup uses it.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduleTemplate {
+    pub checkpoints: Vec<TemplateCheckpoint>,
+    /// The number of the lockups created from the template, a used template can't be changed.
+    pub num_lockups: u64,
+}
+
+impl ScheduleTemplate {
+    pub fn new(checkpoints: Vec<TemplateCheckpoint>) -> Self {
+        Self {
+            checkpoints,
+            num_lockups: 0,
+        }
+    }
+
+    pub fn is_used(&self) -> bool {
+        self.num_lockups > 0
+    }
+
+    /// The balances are rounded down, so the last checkpoint of 100% gets the dust.
+    /// Fails if a checkpoint is past the last timestamp.
+    pub fn to_schedule(&self, total_balance: NearToken, start: u128) -> Result<Schedule, String> {
+        self.checkpoints
+            .iter()
+            .map(|checkpoint| {
+                Ok(Checkpoint {
+                    timestamp: shift_timestamp(start, checkpoint.offset)?,
+                    balance: NearToken::from_yoctonear(mul_div(
+                        total_balance.as_yoctonear(),
+                        checkpoint.basis_points as u128,
+                        BASIS_POINTS,
+                        Rounding::Floor,
+                    )),
+                    interpolation: checkpoint.interpolation,
+                })
+            })
+            .collect::<Result<_, String>>()
+            .map(Schedule)
+    }
+
+    /// The checkpoints are validated as the schedule of a total of `BASIS_POINTS`,
+    /// where every balance is exact.
+    pub fn validate(&self) -> Result<(), String> {
+        ensure!(
+            self.checkpoints
+                .last()
+                .is_some_and(|checkpoint| checkpoint.basis_points as u128 == BASIS_POINTS),
+            "the last checkpoint of a template has to unlock 10000 basis points"
+        );
+        let total_balance = NearToken::from_yoctonear(BASIS_POINTS);
+        self.to_schedule(total_balance, 0)?.validate(total_balance)
+    }
+}
+
+/// The `ft_on_transfer` message of a lockup that uses a template.
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct TemplateLockupCreate {
+    pub template_id: TemplateId,
+    pub account_id: AccountId,
+    /// The unix-timestamp in seconds the template offsets are counted from.
+    pub start: u128,
+}
+
+impl TemplateLockupCreate {
+    /// Returns `None` if the message doesn't reference a template.
+    pub fn from_msg(msg: &str) -> Option<serde_json::Result<Self>> {
+        let value: serde_json::Value = serde_json::from_str(msg).ok()?;
+        value.get("template_id")?;
+        Some(serde_json::from_value(value))
+    }
+
+    /// The lockup of the whole transferred amount.
+    pub fn to_lockup_create(
+        &self,
+        template: &ScheduleTemplate,
+        amount: NearToken,
+    ) -> Result<LockupCreate, String> {
+        Ok(LockupCreate {
+            schedule: template.to_schedule(amount, self.start)?.into(),
+            ..LockupCreate::new_unlocked(self.account_id.clone(), amount)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(offset: u128, basis_points: u16) -> TemplateCheckpoint {
+        TemplateCheckpoint {
+            offset,
+            basis_points,
+            interpolation: Interpolation::Linear,
+        }
+    }
+
+    #[test]
+    fn test_template_to_schedule() {
+        let template = ScheduleTemplate::new(vec![
+            checkpoint(0, 0),
+            checkpoint(100, 3333),
+            checkpoint(200, 10_000),
+        ]);
+        template.validate().unwrap();
+        let schedule = template
+            .to_schedule(NearToken::from_yoctonear(10), 1000)
+            .unwrap();
+        assert_eq!(
+            schedule
+                .0
+                .iter()
+                .map(|checkpoint| (checkpoint.timestamp, checkpoint.balance.as_yoctonear()))
+                .collect::<Vec<_>>(),
+            vec![(1000, 0), (1100, 3), (1200, 10)]
+        );
+        schedule.validate(NearToken::from_yoctonear(10)).unwrap();
+        assert_eq!(
+            template.to_schedule(NearToken::from_yoctonear(10), u128::MAX - 100),
+            Err("timestamp overflow".to_string())
+        );
+    }
+
+    #[test]
+    fn test_template_validate() {
+        assert_eq!(
+            ScheduleTemplate::new(vec![checkpoint(0, 0), checkpoint(100, 9_999)]).validate(),
+            Err("the last checkpoint of a template has to unlock 10000 basis points".to_string())
+        );
+        assert!(
+            ScheduleTemplate::new(vec![checkpoint(0, 0), checkpoint(0, 10_000)])
+                .validate()
+                .is_err()
+        );
+        assert!(
+            ScheduleTemplate::new(vec![checkpoint(0, 100), checkpoint(100, 10_000)])
+                .validate()
+                .is_err()
+        );
+        assert!(ScheduleTemplate::new(vec![]).validate().is_err());
+    }
+
+    #[test]
+    fn test_template_lockup_create_from_msg() {
+        let msg = r#"{"template_id": "cliff", "account_id": "x.near", "start": 1000}"#;
+        let template_create = TemplateLockupCreate::from_msg(msg).unwrap().unwrap();
+        assert_eq!(template_create.template_id, "cliff");
+        assert_eq!(template_create.start, 1000);
+        assert!(
+            TemplateLockupCreate::from_msg(r#"{"template_id": "cliff"}"#)
+                .unwrap()
+                .is_err()
+        );
+        assert!(TemplateLockupCreate::from_msg(r#"{"account_id": "x.near"}"#).is_none());
+        assert!(TemplateLockupCreate::from_msg("[]").is_none());
+    }
+}
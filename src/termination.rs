@@ -1,15 +1,323 @@
-use crate::{lockup::Lockup, schedule::Schedule, util::ZERO_NEAR};
+use crate::{
+    lockup::{Lockup, LockupIndex},
+    merkle::{merkle_leaf, verify_merkle_proof},
+    schedule::{PeekedKeyMap, ScheduleAnchor, ScheduleSpec, BASIS_POINTS, MAX_TGE_TIMESTAMP},
+    util::{ensure, expect_valid, mul_div, Rounding, ZERO_NEAR},
+};
 use near_sdk::{
-    json_types::{Base58CryptoHash, U128},
-    near, AccountId, CryptoHash, NearToken,
+    env,
+    json_types::{Base58CryptoHash, Base64VecU8, U128},
+    near, require,
+    serde::{
+        de::{self, MapAccess, SeqAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    },
+    AccountId, CryptoHash, NearToken, PublicKey,
 };
+use std::{convert::TryInto, fmt};
 
 #[near(serializers = [borsh, json])]
 #[derive(Debug, PartialEq, Clone)]
 pub enum VestingConditions {
     SameAsLockupSchedule,
     Hash(Base58CryptoHash),
-    Schedule(Schedule),
+    Schedule(ScheduleSpec),
+    /// A commitment from `ScheduleSpec::salted_hash`,
+    /// the salt is revealed together with the schedule on termination.
+    SaltedHash(Base58CryptoHash),
+    /// A root of a Merkle tree of the vesting schedules of a whole cohort, see `merkle_leaf`.
+    /// A proof is revealed together with the schedule on termination.
+    MerkleRoot(Base58CryptoHash),
+    /// An ed25519 key that signs the vesting schedules off-chain, see `signed_schedule_message`.
+    /// The schedule is revealed with a signature on termination. The terminator bumps
+    /// the nonce to revoke the signatures issued before, e.g. when the schedule is corrected.
+    SignedBy {
+        public_key: PublicKey,
+        #[serde(default)]
+        nonce: u64,
+    },
+}
+
+/// The message signed for `VestingConditions::SignedBy`. It's bound to the contract,
+/// the lockup account, the lockup index and the current nonce of the lockup, so a signature
+/// can't be reused for another lockup or after it's revoked.
+pub fn signed_schedule_message(
+    account_id: &AccountId,
+    lockup_index: LockupIndex,
+    nonce: u64,
+    schedule: &ScheduleSpec,
+) -> CryptoHash {
+    let value = borsh::to_vec(&(
+        env::current_account_id(),
+        account_id,
+        lockup_index,
+        nonce,
+        schedule.hash(),
+    ))
+    .unwrap();
+    let mut res = CryptoHash::default();
+    res.copy_from_slice(&env::sha256(&value));
+    res
+}
+
+impl VestingConditions {
+    /// Whether the vesting schedule has to be revealed on termination.
+    pub fn is_hidden(&self) -> bool {
+        !matches!(
+            self,
+            VestingConditions::SameAsLockupSchedule | VestingConditions::Schedule(_)
+        )
+    }
+
+    /// Verifies the revealed schedule of the given lockup against the commitment.
+    pub fn assert_revealed(
+        &self,
+        lockup_index: LockupIndex,
+        account_id: &AccountId,
+        reveal: &ScheduleReveal,
+    ) {
+        let schedule = &reveal.schedule;
+        let matches = match self {
+            VestingConditions::Hash(hash) => CryptoHash::from(*hash) == schedule.hash(),
+            VestingConditions::SaltedHash(hash) => {
+                let salt = reveal
+                    .salt
+                    .as_ref()
+                    .expect("Revealed salt required for the termination");
+                CryptoHash::from(*hash) == schedule.salted_hash(&salt.0)
+            }
+            VestingConditions::MerkleRoot(root) => {
+                let proof: Vec<CryptoHash> = reveal
+                    .merkle_proof
+                    .as_ref()
+                    .expect("Merkle proof required for the termination")
+                    .iter()
+                    .map(|&node| node.into())
+                    .collect();
+                verify_merkle_proof(merkle_leaf(account_id, schedule), &proof, &(*root).into())
+            }
+            VestingConditions::SignedBy { public_key, nonce } => {
+                let signature: [u8; 64] = reveal
+                    .signature
+                    .as_ref()
+                    .expect("Signature required for the termination")
+                    .0
+                    .as_slice()
+                    .try_into()
+                    .expect("Invalid signature length");
+                // The first byte is the curve type.
+                let public_key: [u8; 32] = public_key.as_bytes()[1..].try_into().unwrap();
+                require!(
+                    env::ed25519_verify(
+                        &signature,
+                        &signed_schedule_message(account_id, lockup_index, *nonce, schedule),
+                        &public_key
+                    ),
+                    "The revealed schedule signature is invalid"
+                );
+                true
+            }
+            VestingConditions::SameAsLockupSchedule | VestingConditions::Schedule(_) => false,
+        };
+        require!(matches, "The revealed schedule hash doesn't match");
+    }
+}
+
+/// A hidden vesting schedule revealed on termination, with the data to verify the commitment.
+/// Only the data of the kind of the commitment is required.
+#[near(serializers = [json])]
+#[serde(remote = "Self")]
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScheduleReveal {
+    pub schedule: ScheduleSpec,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<Base64VecU8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merkle_proof: Option<Vec<Base58CryptoHash>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Base64VecU8>,
+}
+
+impl ScheduleReveal {
+    const FIELDS: [&'static str; 4] = ["schedule", "salt", "merkle_proof", "signature"];
+}
+
+// A bare schedule is still accepted for the plain hash commitments, as `hashed_schedule`
+// used to be the schedule alone.
+impl Serialize for ScheduleReveal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.salt.is_none() && self.merkle_proof.is_none() && self.signature.is_none() {
+            self.schedule.serialize(serializer)
+        } else {
+            ScheduleReveal::serialize(self, serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ScheduleReveal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ScheduleRevealVisitor;
+
+        impl<'de> Visitor<'de> for ScheduleRevealVisitor {
+            type Value = ScheduleReveal;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a schedule or a schedule reveal")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                <ScheduleSpec as Deserialize>::deserialize(de::value::SeqAccessDeserializer::new(
+                    seq,
+                ))
+                .map(ScheduleReveal::from)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let Some(key) = map.next_key::<String>()? else {
+                    return Err(de::Error::invalid_length(0, &self));
+                };
+                let is_reveal = ScheduleReveal::FIELDS.contains(&key.as_str());
+                let map = de::value::MapAccessDeserializer::new(PeekedKeyMap::new(key, map));
+                if is_reveal {
+                    ScheduleReveal::deserialize(map)
+                } else {
+                    <ScheduleSpec as Deserialize>::deserialize(map).map(ScheduleReveal::from)
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ScheduleRevealVisitor)
+    }
+}
+
+impl From<ScheduleSpec> for ScheduleReveal {
+    fn from(schedule: ScheduleSpec) -> Self {
+        Self {
+            schedule,
+            salt: None,
+            merkle_proof: None,
+            signature: None,
+        }
+    }
+}
+
+/// A termination that can be cancelled by the terminator until it's effective,
+/// and can be finalized by anyone after that.
+#[near(serializers = [borsh, json])]
+#[derive(Debug, PartialEq, Clone)]
+pub struct PendingTermination {
+    pub announced_timestamp: U128,
+    pub termination_timestamp: U128,
+    #[serde(default, skip_serializing_if = "Leaver::is_good")]
+    pub leaver: Leaver,
+}
+
+/// What a bad leaver keeps of the vested balance. The rest goes to the beneficiary.
+#[near(serializers = [borsh, json])]
+#[derive(Debug, PartialEq, Clone)]
+pub enum TerminationPolicy {
+    /// Only the claimed balance is kept, the vested balance that isn't claimed yet is forfeited.
+    ForfeitUnclaimed,
+    /// The given share of the vested balance is kept, but never less than the claimed balance.
+    KeepVestedShare { basis_points: u16 },
+}
+
+impl TerminationPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if let TerminationPolicy::KeepVestedShare { basis_points } = self {
+            ensure!(
+                *basis_points as u128 <= BASIS_POINTS,
+                "basis_points can't exceed 10000"
+            );
+        }
+        Ok(())
+    }
+
+    pub fn assert_valid(&self) {
+        expect_valid(self.validate());
+    }
+
+    /// The part of the vested balance the account keeps.
+    pub fn kept_balance(&self, vested_balance: NearToken, claimed_balance: NearToken) -> NearToken {
+        let kept_balance = match self {
+            TerminationPolicy::ForfeitUnclaimed => ZERO_NEAR,
+            TerminationPolicy::KeepVestedShare { basis_points } => {
+                NearToken::from_yoctonear(mul_div(
+                    vested_balance.as_yoctonear(),
+                    *basis_points as u128,
+                    BASIS_POINTS,
+                    Rounding::Floor,
+                ))
+            }
+        };
+        // The claimed balance can't be taken back.
+        std::cmp::min(std::cmp::max(kept_balance, claimed_balance), vested_balance)
+    }
+}
+
+/// Which clause of the termination applies.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Leaver {
+    /// The account keeps the whole vested balance.
+    #[default]
+    Good,
+    /// The account keeps what the `TerminationPolicy` of the lockup allows.
+    Bad,
+}
+
+impl Leaver {
+    pub fn is_good(&self) -> bool {
+        *self == Leaver::Good
+    }
+}
+
+/// Where the unvested and the forfeited balances go on termination.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum TerminationDisposition {
+    /// Returned to the beneficiary.
+    #[default]
+    Beneficiary,
+    /// Transferred to a fixed treasury account, e.g. a community pool.
+    Treasury { account_id: AccountId },
+    /// Burned with the `ft_burn` method of the token.
+    Burn,
+}
+
+impl TerminationDisposition {
+    pub fn is_beneficiary(&self) -> bool {
+        *self == TerminationDisposition::Beneficiary
+    }
+}
+
+/// The balances moved by a termination.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TerminationOutcome {
+    /// The balance that isn't vested by the termination timestamp.
+    pub unvested_balance: NearToken,
+    /// The vested balance forfeited by a bad leaver.
+    pub forfeited_balance: NearToken,
+    pub beneficiary_id: AccountId,
+    pub disposition: TerminationDisposition,
+}
+
+impl TerminationOutcome {
+    /// The balance that is disposed of, by default transferred to the beneficiary.
+    pub fn refund_balance(&self) -> NearToken {
+        self.unvested_balance.saturating_add(self.forfeited_balance)
+    }
+}
+
+/// A refund of `terminate_many` aggregated per beneficiary and disposition, together with
+/// the lockups it's collected from as they were before the termination.
+#[near(serializers = [json])]
+#[derive(Debug, PartialEq, Clone)]
+pub struct TerminationRefund {
+    pub disposition: TerminationDisposition,
+    pub beneficiary_id: AccountId,
+    pub amount: NearToken,
+    pub lockups: Vec<(LockupIndex, Lockup)>,
 }
 
 #[near(serializers = [borsh, json])]
@@ -20,44 +328,288 @@ pub struct TerminationConfig {
     pub beneficiary_id: AccountId,
     /// An optional vesting schedule
     pub vesting_schedule: VestingConditions,
+    /// The account ID that can terminate the lockup, the funding sender by default.
+    pub terminator_id: AccountId,
+    /// The account ID the termination authority is being handed over to.
+    /// It only becomes the terminator once it accepts the handover.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_terminator_id: Option<AccountId>,
+    /// The clause applied to bad leavers. The lockup can't be terminated
+    /// as a bad leaver without it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination_policy: Option<TerminationPolicy>,
+    /// Where the refunds go, the beneficiary by default. A failed transfer is restored
+    /// as an unlocked lockup of its receiver, a failed burn is kept to be retried.
+    #[serde(
+        default,
+        skip_serializing_if = "TerminationDisposition::is_beneficiary"
+    )]
+    pub termination_disposition: TerminationDisposition,
 }
 
 impl Lockup {
+    fn termination_config_mut(&mut self) -> &mut TerminationConfig {
+        self.termination_config
+            .as_mut()
+            .expect("No termination config")
+    }
+
+    pub fn assert_terminator(&self, account_id: &AccountId) {
+        let termination_config = self
+            .termination_config
+            .as_ref()
+            .expect("No termination config");
+        require!(
+            &termination_config.terminator_id == account_id,
+            "Only the terminator can terminate the lockup"
+        );
+    }
+
+    /// Reassigns the refunds of the termination, e.g. when the treasury moves to another account.
+    /// Only the current beneficiary can do it.
+    pub fn set_beneficiary(&mut self, account_id: &AccountId, beneficiary_id: AccountId) {
+        let termination_config = self.termination_config_mut();
+        require!(
+            &termination_config.beneficiary_id == account_id,
+            "Only the beneficiary can reassign the lockup refunds"
+        );
+        termination_config.beneficiary_id = beneficiary_id;
+    }
+
+    /// The first step of the handover, the new terminator has to accept it.
+    /// `None` cancels the pending handover.
+    pub fn propose_terminator(&mut self, account_id: &AccountId, terminator_id: Option<AccountId>) {
+        self.assert_terminator(account_id);
+        self.termination_config_mut().pending_terminator_id = terminator_id;
+    }
+
+    pub fn accept_terminator(&mut self, account_id: &AccountId) {
+        let termination_config = self.termination_config_mut();
+        require!(
+            termination_config.pending_terminator_id.as_ref() == Some(account_id),
+            "Only the pending terminator can accept the handover"
+        );
+        termination_config.terminator_id = termination_config.pending_terminator_id.take().unwrap();
+    }
+
+    /// Verifies the revealed schedule against the commitment and the lockup schedule.
+    fn assert_valid_reveal(
+        &self,
+        lockup_index: LockupIndex,
+        vesting_conditions: &VestingConditions,
+        reveal: &ScheduleReveal,
+    ) {
+        vesting_conditions.assert_revealed(lockup_index, &self.account_id, reveal);
+        reveal.schedule.assert_valid(self.schedule.total_balance());
+        if self.anchor == ScheduleAnchor::Tge {
+            expect_valid(reveal.schedule.validate_shift(MAX_TGE_TIMESTAMP));
+        }
+        self.schedule
+            .assert_valid_termination_schedule(&reveal.schedule);
+    }
+
+    /// Revokes the signatures of the vesting schedule issued so far, returns the new nonce.
+    pub fn revoke_schedule_signatures(&mut self, account_id: &AccountId) -> u64 {
+        self.assert_terminator(account_id);
+        let nonce = match &mut self.termination_config_mut().vesting_schedule {
+            VestingConditions::SignedBy { nonce, .. } => Some(nonce),
+            _ => None,
+        }
+        .expect("The vesting schedule is not signed");
+        *nonce += 1;
+        *nonce
+    }
+
+    /// Replaces the hidden vesting schedule with the revealed one,
+    /// so the vested balance is known before the termination.
+    pub fn reveal_vesting_schedule(&mut self, lockup_index: LockupIndex, reveal: &ScheduleReveal) {
+        let vesting_conditions = &self
+            .termination_config
+            .as_ref()
+            .expect("No termination config")
+            .vesting_schedule;
+        require!(
+            vesting_conditions.is_hidden(),
+            "The vesting schedule is not hidden"
+        );
+        self.assert_valid_reveal(lockup_index, vesting_conditions, reveal);
+        self.termination_config.as_mut().unwrap().vesting_schedule =
+            VestingConditions::Schedule(reveal.schedule.clone());
+    }
+
+    /// Announces the termination at `termination_timestamp`. A hidden vesting schedule
+    /// has to be revealed first, so anyone can finalize the termination.
+    pub fn schedule_termination(
+        &mut self,
+        lockup_index: LockupIndex,
+        leaver: Leaver,
+        current_timestamp: U128,
+        termination_timestamp: U128,
+        rounding: Rounding,
+        tge_timestamp: Option<u128>,
+    ) {
+        require!(
+            self.pending_termination.is_none(),
+            "The termination is already scheduled"
+        );
+        require!(
+            termination_timestamp > current_timestamp,
+            "expected termination_timestamp > now"
+        );
+        // Fails now if the termination would fail.
+        self.clone().terminate_as(
+            lockup_index,
+            leaver,
+            None,
+            termination_timestamp,
+            rounding,
+            tge_timestamp,
+        );
+        self.pending_termination = Some(PendingTermination {
+            announced_timestamp: current_timestamp,
+            termination_timestamp,
+            leaver,
+        });
+    }
+
+    pub fn cancel_termination(&mut self, current_timestamp: U128) {
+        let pending_termination = self
+            .pending_termination
+            .take()
+            .expect("No termination is scheduled");
+        require!(
+            current_timestamp < pending_termination.termination_timestamp,
+            "The termination is already effective"
+        );
+    }
+
+    /// The scheduled termination once it's effective.
+    pub fn effective_termination(&self, current_timestamp: U128) -> PendingTermination {
+        let pending_termination = self
+            .pending_termination
+            .as_ref()
+            .expect("No termination is scheduled");
+        require!(
+            current_timestamp >= pending_termination.termination_timestamp,
+            "The termination is not effective yet"
+        );
+        pending_termination.clone()
+    }
+
+    /// The balance that stays with the account on termination at the given time,
+    /// `None` while the vesting schedule is hidden.
+    pub fn vested_balance(
+        &self,
+        current_timestamp: U128,
+        rounding: Rounding,
+        tge_timestamp: Option<u128>,
+    ) -> Option<NearToken> {
+        let vesting_schedule = match &self.termination_config {
+            None => return Some(self.schedule.total_balance()),
+            Some(termination_config) => match &termination_config.vesting_schedule {
+                VestingConditions::SameAsLockupSchedule => &self.schedule,
+                VestingConditions::Schedule(schedule) => schedule,
+                _hidden => return None,
+            },
+        };
+        Some(match self.schedule_offset(tge_timestamp) {
+            Some(offset) => {
+                let mut vesting_schedule = vesting_schedule.clone();
+                vesting_schedule.shift(offset);
+                vesting_schedule
+                    .unlocked_balance(self.effective_timestamp(current_timestamp), rounding)
+            }
+            None => ZERO_NEAR,
+        })
+    }
+
+    /// Terminates a good leaver, returns the unvested balance and the beneficiary.
     pub fn terminate(
         &mut self,
-        hashed_schedule: Option<Schedule>,
+        lockup_index: LockupIndex,
+        hashed_schedule: Option<ScheduleReveal>,
         termination_timestamp: U128,
+        rounding: Rounding,
+        tge_timestamp: Option<u128>,
     ) -> (NearToken, AccountId) {
+        let outcome = self.terminate_as(
+            lockup_index,
+            Leaver::Good,
+            hashed_schedule,
+            termination_timestamp,
+            rounding,
+            tge_timestamp,
+        );
+        (outcome.unvested_balance, outcome.beneficiary_id)
+    }
+
+    pub fn terminate_as(
+        &mut self,
+        lockup_index: LockupIndex,
+        leaver: Leaver,
+        hashed_schedule: Option<ScheduleReveal>,
+        termination_timestamp: U128,
+        rounding: Rounding,
+        tge_timestamp: Option<u128>,
+    ) -> TerminationOutcome {
+        // The termination ends the pause, so the kept balance unlocks after it.
+        if let Some(from) = self.paused_since() {
+            if termination_timestamp > from {
+                self.resume_vesting(termination_timestamp);
+            } else {
+                self.paused_intervals.pop();
+            }
+        }
         let termination_config = self
             .termination_config
             .take()
             .expect("No termination config");
+        // An immediate termination replaces the announced one.
+        self.pending_termination = None;
         let total_balance = self.schedule.total_balance();
-        let vested_balance = match &termination_config.vesting_schedule {
+        let offset = self.schedule_offset(tge_timestamp);
+        let vesting_schedule = match &termination_config.vesting_schedule {
             VestingConditions::SameAsLockupSchedule => &self.schedule,
-            VestingConditions::Hash(hash) => {
-                let schedule = hashed_schedule
+            VestingConditions::Schedule(schedule) => schedule,
+            hidden => {
+                let reveal = hashed_schedule
                     .as_ref()
                     .expect("Revealed schedule required for the termination");
-                let hash: CryptoHash = (*hash).into();
-                assert_eq!(
-                    hash,
-                    schedule.hash(),
-                    "The revealed schedule hash doesn't match"
-                );
-                schedule.assert_valid(total_balance);
-                self.schedule.assert_valid_termination_schedule(schedule);
-                schedule
+                self.assert_valid_reveal(lockup_index, hidden, reveal);
+                &reveal.schedule
             }
-            VestingConditions::Schedule(schedule) => schedule,
+        };
+        let vested_balance = match offset {
+            Some(offset) => {
+                let mut vesting_schedule = vesting_schedule.clone();
+                vesting_schedule.shift(offset);
+                vesting_schedule.unlocked_balance(termination_timestamp, rounding)
+            }
+            // Nothing is vested until the TGE timestamp is set.
+            None => ZERO_NEAR,
+        };
+        let kept_balance = match leaver {
+            Leaver::Good => vested_balance,
+            Leaver::Bad => termination_config
+                .termination_policy
+                .as_ref()
+                .expect("The lockup has no termination policy for bad leavers")
+                .kept_balance(vested_balance, self.claimed_balance),
+        };
+        if kept_balance < total_balance {
+            if let Some(offset) = offset {
+                // The terminated schedule is stored in unix timestamps.
+                self.schedule.shift(offset);
+                self.anchor = ScheduleAnchor::Absolute;
+            }
+            self.schedule.terminate(kept_balance, termination_timestamp);
         }
-        .unlocked_balance(termination_timestamp);
-        let unvested_balance = total_balance.saturating_sub(vested_balance);
-        if unvested_balance > ZERO_NEAR {
-            self.schedule
-                .terminate(vested_balance, termination_timestamp);
+        TerminationOutcome {
+            unvested_balance: total_balance.saturating_sub(vested_balance),
+            forfeited_balance: vested_balance.saturating_sub(kept_balance),
+            beneficiary_id: termination_config.beneficiary_id,
+            disposition: termination_config.termination_disposition,
         }
-        (unvested_balance, termination_config.beneficiary_id)
     }
 }
 
@@ -65,6 +617,18 @@ impl Lockup {
 mod tests {
 
     use super::*;
+    use crate::{
+        lockup::LockupCreate,
+        merkle::{merkle_proof, merkle_root},
+        schedule::Schedule,
+        test_utils::{contract_with_lockups, terminable_lockup, terminable_lockup_create, FtMock},
+        Contract,
+    };
+    use near_sdk::{
+        serde_json,
+        test_utils::{accounts, VMContextBuilder},
+        testing_env, PromiseOrValue,
+    };
 
     #[test]
     fn test_terminate() {
@@ -74,15 +638,23 @@ mod tests {
         let schedule = Schedule::new_unlocked_since(total_balance, timestamp);
         let mut lockup = Lockup {
             account_id: account_id.clone(),
-            schedule: schedule.clone(),
+            schedule: schedule.clone().into(),
             claimed_balance: ZERO_NEAR,
             termination_config: Some(TerminationConfig {
                 beneficiary_id: account_id.clone(),
                 vesting_schedule: VestingConditions::SameAsLockupSchedule,
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
             }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
         };
 
-        let (unvested_amount, beneficiary) = lockup.terminate(None, timestamp);
+        let (unvested_amount, beneficiary) =
+            lockup.terminate(0, None, timestamp, Rounding::Floor, None);
         assert_eq!(unvested_amount.as_yoctonear(), 0);
         assert_eq!(beneficiary, account_id);
     }
@@ -97,12 +669,15 @@ mod tests {
 
         let mut lockup = Lockup {
             account_id: account_id.clone(),
-            schedule: schedule.clone(),
+            schedule: schedule.clone().into(),
             claimed_balance: ZERO_NEAR,
             termination_config: None,
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
         };
 
-        lockup.terminate(None, timestamp);
+        lockup.terminate(0, None, timestamp, Rounding::Floor, None);
     }
 
     #[test]
@@ -115,15 +690,22 @@ mod tests {
 
         let mut lockup = Lockup {
             account_id: account_id.clone(),
-            schedule: schedule.clone(),
+            schedule: schedule.clone().into(),
             claimed_balance: ZERO_NEAR,
             termination_config: Some(TerminationConfig {
                 beneficiary_id: account_id.clone(),
                 vesting_schedule: VestingConditions::Hash(schedule.hash().into()),
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
             }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
         };
 
-        lockup.terminate(None, timestamp);
+        lockup.terminate(0, None, timestamp, Rounding::Floor, None);
     }
 
     #[test]
@@ -135,15 +717,28 @@ mod tests {
 
         let mut lockup = Lockup {
             account_id: account_id.clone(),
-            schedule: schedule.clone(),
+            schedule: schedule.clone().into(),
             claimed_balance: ZERO_NEAR,
             termination_config: Some(TerminationConfig {
                 beneficiary_id: account_id.clone(),
                 vesting_schedule: VestingConditions::Hash(schedule.hash().into()),
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
             }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
         };
 
-        let (unvested_amount, beneficiary) = lockup.terminate(Some(schedule), timestamp);
+        let (unvested_amount, beneficiary) = lockup.terminate(
+            0,
+            Some(ScheduleSpec::from(schedule).into()),
+            timestamp,
+            Rounding::Floor,
+            None,
+        );
         assert_eq!(unvested_amount.as_yoctonear(), 0);
         assert_eq!(beneficiary, account_id);
     }
@@ -157,16 +752,1019 @@ mod tests {
 
         let mut lockup = Lockup {
             account_id: account_id.clone(),
-            schedule: schedule.clone(),
+            schedule: schedule.clone().into(),
             claimed_balance: ZERO_NEAR,
             termination_config: Some(TerminationConfig {
                 beneficiary_id: account_id.clone(),
-                vesting_schedule: VestingConditions::Schedule(schedule),
+                vesting_schedule: VestingConditions::Schedule(schedule.into()),
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
             }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
         };
 
-        let (unvested_amount, beneficiary) = lockup.terminate(None, timestamp);
+        let (unvested_amount, beneficiary) =
+            lockup.terminate(0, None, timestamp, Rounding::Floor, None);
         assert_eq!(unvested_amount.as_yoctonear(), 0);
         assert_eq!(beneficiary, account_id);
     }
+
+    #[test]
+    fn test_terminate_tge_relative() {
+        let account_id: AccountId = "x.near".parse().unwrap();
+        let total_balance = NearToken::from_yoctonear(1000);
+        let schedule = Schedule::new_cliff_linear(total_balance, 0.into(), 0.into(), 1000.into());
+        let vesting_schedule =
+            Schedule::new_cliff_linear(total_balance, 0.into(), 0.into(), 500.into());
+        let lockup = Lockup {
+            account_id: account_id.clone(),
+            schedule: schedule.clone().into(),
+            claimed_balance: ZERO_NEAR,
+            termination_config: Some(TerminationConfig {
+                beneficiary_id: account_id.clone(),
+                vesting_schedule: VestingConditions::Hash(vesting_schedule.hash().into()),
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
+            }),
+            anchor: ScheduleAnchor::Tge,
+            pending_termination: None,
+            paused_intervals: vec![],
+        };
+
+        // Nothing is vested before the TGE timestamp is set.
+        let mut terminated = lockup.clone();
+        let (unvested_amount, _) = terminated.terminate(
+            0,
+            Some(ScheduleSpec::from(vesting_schedule.clone()).into()),
+            U128(100),
+            Rounding::Floor,
+            None,
+        );
+        assert_eq!(unvested_amount, total_balance);
+        assert_eq!(terminated.schedule.total_balance(), ZERO_NEAR);
+
+        // The revealed schedule is relative to the TGE timestamp as well.
+        let mut terminated = lockup;
+        let (unvested_amount, _) = terminated.terminate(
+            0,
+            Some(ScheduleSpec::from(vesting_schedule).into()),
+            U128(10_100),
+            Rounding::Floor,
+            Some(10_000),
+        );
+        assert_eq!(unvested_amount.as_yoctonear(), 800);
+        assert_eq!(terminated.anchor, ScheduleAnchor::Absolute);
+        assert_eq!(terminated.schedule.start_timestamp(), 10_000);
+        assert_eq!(terminated.schedule.finish_timestamp(), 10_200);
+        assert_eq!(
+            terminated
+                .unlocked_balance(U128(10_200), Rounding::Floor, Some(10_000))
+                .as_yoctonear(),
+            200
+        );
+    }
+
+    fn salted_lockup(salt: &[u8]) -> (Lockup, Schedule) {
+        let account_id: AccountId = "x.near".parse().unwrap();
+        let total_balance = NearToken::from_yoctonear(1000);
+        let schedule = Schedule::new_cliff_linear(total_balance, 0.into(), 0.into(), 1000.into());
+        let vesting_schedule: ScheduleSpec =
+            Schedule::new_cliff_linear(total_balance, 0.into(), 0.into(), 500.into()).into();
+        let lockup = Lockup {
+            account_id: account_id.clone(),
+            schedule: schedule.into(),
+            claimed_balance: ZERO_NEAR,
+            termination_config: Some(TerminationConfig {
+                beneficiary_id: account_id,
+                vesting_schedule: VestingConditions::SaltedHash(
+                    vesting_schedule.salted_hash(salt).into(),
+                ),
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
+            }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
+        };
+        (lockup, vesting_schedule.to_schedule())
+    }
+
+    #[test]
+    fn test_terminate_salted_hash() {
+        let salt = vec![42; 32];
+        let (mut lockup, vesting_schedule) = salted_lockup(&salt);
+        let (unvested_amount, _) = lockup.terminate(
+            0,
+            Some(ScheduleReveal {
+                schedule: vesting_schedule.into(),
+                salt: Some(salt.into()),
+                merkle_proof: None,
+                signature: None,
+            }),
+            U128(100),
+            Rounding::Floor,
+            None,
+        );
+        assert_eq!(unvested_amount.as_yoctonear(), 800);
+    }
+
+    #[test]
+    fn test_schedule_reveal_json() {
+        let schedule: ScheduleSpec = Schedule::new_unlocked(NearToken::from_yoctonear(1)).into();
+        let bare = serde_json::to_string(&schedule).unwrap();
+        let reveal: ScheduleReveal = serde_json::from_str(&bare).unwrap();
+        assert_eq!(reveal, ScheduleReveal::from(schedule.clone()));
+        assert_eq!(serde_json::to_string(&reveal).unwrap(), bare);
+
+        let spec = ScheduleSpec::Linear {
+            total_balance: NearToken::from_yoctonear(1),
+            start: 0,
+            finish: 10,
+        };
+        let reveal: ScheduleReveal =
+            serde_json::from_str(r#"{"Linear": {"total_balance": "1", "start": 0, "finish": 10}}"#)
+                .unwrap();
+        assert_eq!(reveal, ScheduleReveal::from(spec));
+
+        let salted = ScheduleReveal {
+            salt: Some(vec![42; 4].into()),
+            ..ScheduleReveal::from(schedule)
+        };
+        let json = serde_json::to_string(&salted).unwrap();
+        assert!(json.starts_with(r#"{"schedule":"#));
+        assert_eq!(
+            serde_json::from_str::<ScheduleReveal>(&json).unwrap(),
+            salted
+        );
+        let reordered = format!(r#"{{"salt": "KioqKg==", "schedule": {}}}"#, bare);
+        assert_eq!(
+            serde_json::from_str::<ScheduleReveal>(&reordered).unwrap(),
+            salted
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The revealed schedule hash doesn't match")]
+    fn test_terminate_salted_hash_wrong_salt() {
+        let (mut lockup, vesting_schedule) = salted_lockup(&[42; 32]);
+        lockup.terminate(
+            0,
+            Some(ScheduleReveal {
+                schedule: vesting_schedule.into(),
+                salt: Some(vec![43; 32].into()),
+                merkle_proof: None,
+                signature: None,
+            }),
+            U128(100),
+            Rounding::Floor,
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Revealed salt required for the termination")]
+    fn test_terminate_salted_hash_no_salt() {
+        let (mut lockup, vesting_schedule) = salted_lockup(&[42; 32]);
+        lockup.terminate(
+            0,
+            Some(ScheduleSpec::from(vesting_schedule).into()),
+            U128(100),
+            Rounding::Floor,
+            None,
+        );
+    }
+
+    /// A lockup of `x.near` committed to a cohort root, with the reveals of the whole cohort.
+    fn merkle_lockup() -> (Lockup, Vec<ScheduleReveal>) {
+        let total_balance = NearToken::from_yoctonear(1000);
+        let cohort: Vec<(AccountId, ScheduleSpec)> = ["a.near", "x.near", "c.near"]
+            .iter()
+            .zip([500, 600, 700])
+            .map(|(account_id, finish)| {
+                (
+                    account_id.parse().unwrap(),
+                    Schedule::new_cliff_linear(total_balance, 0.into(), 0.into(), finish.into())
+                        .into(),
+                )
+            })
+            .collect();
+        let leaves: Vec<CryptoHash> = cohort
+            .iter()
+            .map(|(account_id, schedule)| merkle_leaf(account_id, schedule))
+            .collect();
+        let lockup = Lockup {
+            account_id: "x.near".parse().unwrap(),
+            schedule: Schedule::new_cliff_linear(total_balance, 0.into(), 0.into(), 1000.into())
+                .into(),
+            claimed_balance: ZERO_NEAR,
+            termination_config: Some(TerminationConfig {
+                beneficiary_id: "p.near".parse().unwrap(),
+                vesting_schedule: VestingConditions::MerkleRoot(merkle_root(&leaves).into()),
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
+            }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
+        };
+        let reveals = cohort
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, schedule))| ScheduleReveal {
+                schedule,
+                salt: None,
+                merkle_proof: Some(
+                    merkle_proof(&leaves, index)
+                        .into_iter()
+                        .map(Base58CryptoHash::from)
+                        .collect(),
+                ),
+                signature: None,
+            })
+            .collect();
+        (lockup, reveals)
+    }
+
+    #[test]
+    fn test_terminate_merkle_root() {
+        let (mut lockup, mut reveals) = merkle_lockup();
+        let (unvested_amount, _) =
+            lockup.terminate(0, Some(reveals.remove(1)), U128(300), Rounding::Floor, None);
+        assert_eq!(unvested_amount.as_yoctonear(), 500);
+    }
+
+    #[test]
+    #[should_panic(expected = "The revealed schedule hash doesn't match")]
+    fn test_terminate_merkle_root_other_account() {
+        // The schedule of another account of the cohort can't be used.
+        let (mut lockup, mut reveals) = merkle_lockup();
+        lockup.terminate(0, Some(reveals.remove(2)), U128(300), Rounding::Floor, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Merkle proof required for the termination")]
+    fn test_terminate_merkle_root_no_proof() {
+        let (mut lockup, mut reveals) = merkle_lockup();
+        let mut reveal = reveals.remove(1);
+        reveal.merkle_proof = None;
+        lockup.terminate(0, Some(reveal), U128(300), Rounding::Floor, None);
+    }
+
+    fn signed_lockup() -> (Lockup, ScheduleSpec, ed25519_dalek::SigningKey) {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let public_key = PublicKey::from_parts(
+            near_sdk::CurveType::ED25519,
+            signing_key.verifying_key().to_bytes().to_vec(),
+        )
+        .unwrap();
+        let total_balance = NearToken::from_yoctonear(1000);
+        let lockup = Lockup {
+            account_id: "x.near".parse().unwrap(),
+            schedule: Schedule::new_cliff_linear(total_balance, 0.into(), 0.into(), 1000.into())
+                .into(),
+            claimed_balance: ZERO_NEAR,
+            termination_config: Some(TerminationConfig {
+                beneficiary_id: "p.near".parse().unwrap(),
+                vesting_schedule: VestingConditions::SignedBy {
+                    public_key,
+                    nonce: 0,
+                },
+                terminator_id: "t.near".parse().unwrap(),
+                pending_terminator_id: None,
+                termination_policy: None,
+                termination_disposition: TerminationDisposition::Beneficiary,
+            }),
+            anchor: ScheduleAnchor::Absolute,
+            pending_termination: None,
+            paused_intervals: vec![],
+        };
+        lockup.assert_valid(total_balance);
+        let vesting_schedule =
+            Schedule::new_cliff_linear(total_balance, 0.into(), 0.into(), 500.into()).into();
+        (lockup, vesting_schedule, signing_key)
+    }
+
+    fn sign(
+        signing_key: &ed25519_dalek::SigningKey,
+        account_id: &str,
+        lockup_index: LockupIndex,
+        nonce: u64,
+        schedule: &ScheduleSpec,
+    ) -> Base64VecU8 {
+        use ed25519_dalek::Signer;
+        let message =
+            signed_schedule_message(&account_id.parse().unwrap(), lockup_index, nonce, schedule);
+        signing_key.sign(&message).to_bytes().to_vec().into()
+    }
+
+    #[test]
+    fn test_terminate_signed_by() {
+        let (mut lockup, vesting_schedule, signing_key) = signed_lockup();
+        let signature = sign(&signing_key, "x.near", 0, 0, &vesting_schedule);
+        let (unvested_amount, _) = lockup.terminate(
+            0,
+            Some(ScheduleReveal {
+                schedule: vesting_schedule,
+                salt: None,
+                merkle_proof: None,
+                signature: Some(signature),
+            }),
+            U128(300),
+            Rounding::Floor,
+            None,
+        );
+        assert_eq!(unvested_amount.as_yoctonear(), 400);
+    }
+
+    #[test]
+    #[should_panic(expected = "The revealed schedule signature is invalid")]
+    fn test_terminate_signed_by_other_account() {
+        let (mut lockup, vesting_schedule, signing_key) = signed_lockup();
+        let signature = sign(&signing_key, "y.near", 0, 0, &vesting_schedule);
+        lockup.terminate(
+            0,
+            Some(ScheduleReveal {
+                schedule: vesting_schedule,
+                salt: None,
+                merkle_proof: None,
+                signature: Some(signature),
+            }),
+            U128(300),
+            Rounding::Floor,
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The revealed schedule signature is invalid")]
+    fn test_terminate_signed_by_other_lockup() {
+        let (mut lockup, vesting_schedule, signing_key) = signed_lockup();
+        let signature = sign(&signing_key, "x.near", 1, 0, &vesting_schedule);
+        lockup.terminate(
+            0,
+            Some(ScheduleReveal {
+                signature: Some(signature),
+                ..vesting_schedule.into()
+            }),
+            U128(300),
+            Rounding::Floor,
+            None,
+        );
+    }
+
+    #[test]
+    fn test_revoke_schedule_signatures() {
+        let (mut lockup, vesting_schedule, signing_key) = signed_lockup();
+        assert_eq!(
+            lockup.revoke_schedule_signatures(&"t.near".parse().unwrap()),
+            1
+        );
+        let signature = sign(&signing_key, "x.near", 0, 1, &vesting_schedule);
+        lockup.reveal_vesting_schedule(
+            0,
+            &ScheduleReveal {
+                signature: Some(signature),
+                ..vesting_schedule.into()
+            },
+        );
+        assert!(!lockup
+            .termination_config
+            .unwrap()
+            .vesting_schedule
+            .is_hidden());
+    }
+
+    #[test]
+    #[should_panic(expected = "The revealed schedule signature is invalid")]
+    fn test_reveal_revoked_schedule_signature() {
+        let (mut lockup, vesting_schedule, signing_key) = signed_lockup();
+        let signature = sign(&signing_key, "x.near", 0, 0, &vesting_schedule);
+        lockup.revoke_schedule_signatures(&"t.near".parse().unwrap());
+        lockup.reveal_vesting_schedule(
+            0,
+            &ScheduleReveal {
+                signature: Some(signature),
+                ..vesting_schedule.into()
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Signature required for the termination")]
+    fn test_terminate_signed_by_no_signature() {
+        let (mut lockup, vesting_schedule, _) = signed_lockup();
+        lockup.terminate(
+            0,
+            Some(vesting_schedule.into()),
+            U128(300),
+            Rounding::Floor,
+            None,
+        );
+    }
+
+    #[test]
+    fn test_signed_by_secp256k1_key() {
+        let (mut lockup, _, _) = signed_lockup();
+        lockup.termination_config.as_mut().unwrap().vesting_schedule =
+            VestingConditions::SignedBy {
+                public_key: PublicKey::from_parts(near_sdk::CurveType::SECP256K1, vec![1; 64])
+                    .unwrap(),
+                nonce: 0,
+            };
+        assert_eq!(
+            lockup.validate(lockup.schedule.total_balance()),
+            Err("Only ed25519 signing keys are supported".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reveal_vesting_schedule() {
+        let salt = vec![42; 32];
+        let (mut lockup, vesting_schedule) = salted_lockup(&salt);
+        assert_eq!(
+            lockup.vested_balance(U128(100), Rounding::Floor, None),
+            None
+        );
+        lockup.reveal_vesting_schedule(
+            0,
+            &ScheduleReveal {
+                schedule: vesting_schedule.into(),
+                salt: Some(salt.into()),
+                merkle_proof: None,
+                signature: None,
+            },
+        );
+        assert_eq!(
+            lockup.vested_balance(U128(100), Rounding::Floor, None),
+            Some(NearToken::from_yoctonear(200))
+        );
+        let (unvested_amount, _) = lockup.terminate(0, None, U128(100), Rounding::Floor, None);
+        assert_eq!(unvested_amount.as_yoctonear(), 800);
+    }
+
+    #[test]
+    #[should_panic(expected = "The revealed schedule hash doesn't match")]
+    fn test_reveal_vesting_schedule_wrong_salt() {
+        let (mut lockup, vesting_schedule) = salted_lockup(&[42; 32]);
+        lockup.reveal_vesting_schedule(
+            0,
+            &ScheduleReveal {
+                schedule: vesting_schedule.into(),
+                salt: Some(vec![43; 32].into()),
+                merkle_proof: None,
+                signature: None,
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The vesting schedule is not hidden")]
+    fn test_reveal_vesting_schedule_not_hidden() {
+        let salt = vec![42; 32];
+        let (mut lockup, vesting_schedule) = salted_lockup(&salt);
+        let reveal = ScheduleReveal {
+            schedule: vesting_schedule.into(),
+            salt: Some(salt.into()),
+            merkle_proof: None,
+            signature: None,
+        };
+        lockup.reveal_vesting_schedule(0, &reveal);
+        lockup.reveal_vesting_schedule(0, &reveal);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_reveal_vesting_schedule_without_deposit() {
+        let salt = vec![42; 32];
+        let (lockup, vesting_schedule) = salted_lockup(&salt);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(lockup.account_id.clone())
+            .build());
+        let mut contract = Contract::new("token.near".parse().unwrap(), vec![], None);
+        contract.internal_add_lockup(&lockup);
+        contract.reveal_vesting_schedule(
+            0,
+            ScheduleReveal {
+                salt: Some(salt.into()),
+                ..ScheduleSpec::from(vesting_schedule).into()
+            },
+        );
+    }
+
+    #[test]
+    fn test_terminator_handover() {
+        let (mut lockup, _) = salted_lockup(&[42; 32]);
+        let terminator_id: AccountId = "t.near".parse().unwrap();
+        let new_terminator_id: AccountId = "n.near".parse().unwrap();
+        lockup.propose_terminator(&terminator_id, Some(new_terminator_id.clone()));
+        // The current terminator keeps the authority until the handover is accepted.
+        lockup.assert_terminator(&terminator_id);
+        lockup.accept_terminator(&new_terminator_id);
+        let termination_config = lockup.termination_config.as_ref().unwrap();
+        assert_eq!(termination_config.terminator_id, new_terminator_id);
+        assert_eq!(termination_config.pending_terminator_id, None);
+        lockup.assert_terminator(&new_terminator_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the terminator can terminate the lockup")]
+    fn test_propose_terminator_not_terminator() {
+        let (mut lockup, _) = salted_lockup(&[42; 32]);
+        lockup.propose_terminator(&"x.near".parse().unwrap(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the pending terminator can accept the handover")]
+    fn test_accept_terminator_not_pending() {
+        let (mut lockup, _) = salted_lockup(&[42; 32]);
+        lockup.propose_terminator(&"t.near".parse().unwrap(), Some("n.near".parse().unwrap()));
+        lockup.accept_terminator(&"x.near".parse().unwrap());
+    }
+
+    #[test]
+    fn test_set_beneficiary() {
+        let (mut lockup, _) = salted_lockup(&[42; 32]);
+        let beneficiary_id: AccountId = "b.near".parse().unwrap();
+        lockup.set_beneficiary(&"x.near".parse().unwrap(), beneficiary_id.clone());
+        assert_eq!(
+            lockup.termination_config.as_ref().unwrap().beneficiary_id,
+            beneficiary_id
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the beneficiary can reassign the lockup refunds")]
+    fn test_set_beneficiary_not_beneficiary() {
+        let (mut lockup, _) = salted_lockup(&[42; 32]);
+        lockup.set_beneficiary(&"t.near".parse().unwrap(), "b.near".parse().unwrap());
+    }
+
+    fn scheduled_termination_lockup() -> Lockup {
+        let salt = vec![42; 32];
+        let (mut lockup, vesting_schedule) = salted_lockup(&salt);
+        lockup.reveal_vesting_schedule(
+            0,
+            &ScheduleReveal {
+                schedule: vesting_schedule.into(),
+                salt: Some(salt.into()),
+                merkle_proof: None,
+                signature: None,
+            },
+        );
+        lockup.schedule_termination(0, Leaver::Good, U128(50), U128(100), Rounding::Floor, None);
+        lockup
+    }
+
+    #[test]
+    fn test_schedule_termination() {
+        let mut lockup = scheduled_termination_lockup();
+        assert_eq!(
+            lockup.pending_termination,
+            Some(PendingTermination {
+                announced_timestamp: U128(50),
+                termination_timestamp: U128(100),
+                leaver: Leaver::Good,
+            })
+        );
+        // The vesting schedule is revealed before the announcement.
+        assert!(!lockup
+            .termination_config
+            .as_ref()
+            .unwrap()
+            .vesting_schedule
+            .is_hidden());
+        let termination_timestamp = lockup
+            .effective_termination(U128(150))
+            .termination_timestamp;
+        assert_eq!(termination_timestamp, U128(100));
+        let (unvested_amount, _) =
+            lockup.terminate(0, None, termination_timestamp, Rounding::Floor, None);
+        assert_eq!(unvested_amount.as_yoctonear(), 800);
+        assert_eq!(lockup.pending_termination, None);
+    }
+
+    #[test]
+    fn test_cancel_termination() {
+        let mut lockup = scheduled_termination_lockup();
+        lockup.cancel_termination(U128(99));
+        assert_eq!(lockup.pending_termination, None);
+        assert!(lockup.termination_config.is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "The termination is already effective")]
+    fn test_cancel_effective_termination() {
+        let mut lockup = scheduled_termination_lockup();
+        lockup.cancel_termination(U128(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "The termination is not effective yet")]
+    fn test_finalize_termination_too_early() {
+        let lockup = scheduled_termination_lockup();
+        lockup.effective_termination(U128(99));
+    }
+
+    #[test]
+    #[should_panic(expected = "The termination is already scheduled")]
+    fn test_schedule_termination_twice() {
+        let mut lockup = scheduled_termination_lockup();
+        lockup.schedule_termination(0, Leaver::Good, U128(60), U128(200), Rounding::Floor, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Revealed schedule required for the termination")]
+    fn test_schedule_termination_hidden_without_reveal() {
+        let (mut lockup, _) = salted_lockup(&[42; 32]);
+        lockup.schedule_termination(0, Leaver::Good, U128(50), U128(100), Rounding::Floor, None);
+    }
+
+    fn bad_leaver_lockup(
+        termination_policy: Option<TerminationPolicy>,
+        claimed_balance: NearToken,
+    ) -> Lockup {
+        let mut lockup = terminable_lockup();
+        lockup.claimed_balance = claimed_balance;
+        lockup
+            .termination_config
+            .as_mut()
+            .unwrap()
+            .termination_policy = termination_policy;
+        lockup
+    }
+
+    #[test]
+    fn test_terminate_bad_leaver_forfeit_unclaimed() {
+        let mut lockup = bad_leaver_lockup(
+            Some(TerminationPolicy::ForfeitUnclaimed),
+            NearToken::from_yoctonear(100),
+        );
+        let outcome = lockup.terminate_as(0, Leaver::Bad, None, U128(500), Rounding::Floor, None);
+        assert_eq!(outcome.unvested_balance.as_yoctonear(), 500);
+        assert_eq!(outcome.forfeited_balance.as_yoctonear(), 400);
+        assert_eq!(outcome.refund_balance().as_yoctonear(), 900);
+        assert_eq!(lockup.schedule.total_balance().as_yoctonear(), 100);
+        assert_eq!(
+            lockup
+                .unlocked_balance(U128(500), Rounding::Floor, None)
+                .as_yoctonear(),
+            100
+        );
+    }
+
+    #[test]
+    fn test_terminate_bad_leaver_keep_vested_share() {
+        let mut lockup = bad_leaver_lockup(
+            Some(TerminationPolicy::KeepVestedShare { basis_points: 5000 }),
+            ZERO_NEAR,
+        );
+        let outcome = lockup.terminate_as(0, Leaver::Bad, None, U128(500), Rounding::Floor, None);
+        assert_eq!(outcome.unvested_balance.as_yoctonear(), 500);
+        assert_eq!(outcome.forfeited_balance.as_yoctonear(), 250);
+        assert_eq!(lockup.schedule.total_balance().as_yoctonear(), 250);
+    }
+
+    #[test]
+    fn test_terminate_bad_leaver_keeps_claimed_balance() {
+        let mut lockup = bad_leaver_lockup(
+            Some(TerminationPolicy::KeepVestedShare { basis_points: 5000 }),
+            NearToken::from_yoctonear(400),
+        );
+        let outcome = lockup.terminate_as(0, Leaver::Bad, None, U128(500), Rounding::Floor, None);
+        assert_eq!(outcome.forfeited_balance.as_yoctonear(), 100);
+        assert_eq!(lockup.schedule.total_balance().as_yoctonear(), 400);
+    }
+
+    #[test]
+    fn test_terminate_good_leaver_with_policy() {
+        let mut lockup = bad_leaver_lockup(Some(TerminationPolicy::ForfeitUnclaimed), ZERO_NEAR);
+        let outcome = lockup.terminate_as(0, Leaver::Good, None, U128(500), Rounding::Floor, None);
+        assert_eq!(outcome.unvested_balance.as_yoctonear(), 500);
+        assert_eq!(outcome.forfeited_balance, ZERO_NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "The lockup has no termination policy for bad leavers")]
+    fn test_terminate_bad_leaver_without_policy() {
+        let mut lockup = bad_leaver_lockup(None, ZERO_NEAR);
+        lockup.terminate_as(0, Leaver::Bad, None, U128(500), Rounding::Floor, None);
+    }
+
+    #[test]
+    fn test_termination_policy_too_many_basis_points() {
+        let lockup = bad_leaver_lockup(
+            Some(TerminationPolicy::KeepVestedShare {
+                basis_points: 10_001,
+            }),
+            ZERO_NEAR,
+        );
+        assert_eq!(
+            lockup.validate(lockup.schedule.total_balance()),
+            Err("basis_points can't exceed 10000".to_string())
+        );
+    }
+
+    /// A contract with the lockup of `bad_leaver_lockup` and its termination scheduled
+    /// by `p.near` at 100 to be effective at 300.
+    fn contract_with_scheduled_termination(leaver: Leaver) -> (Contract, VMContextBuilder) {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .predecessor_account_id("p.near".parse().unwrap())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_timestamp(100 * 1_000_000_000)
+            .build());
+        let mut contract = Contract::new("token.near".parse().unwrap(), vec![], None);
+        contract.internal_add_lockup(&bad_leaver_lockup(
+            Some(TerminationPolicy::ForfeitUnclaimed),
+            ZERO_NEAR,
+        ));
+        contract.schedule_termination(0, None, U128(300), Some(leaver));
+        (contract, context)
+    }
+
+    #[test]
+    fn test_claim_before_finalize_termination() {
+        let (mut contract, mut context) = contract_with_scheduled_termination(Leaver::Good);
+        testing_env!(context
+            .predecessor_account_id("x.near".parse().unwrap())
+            .block_timestamp(400 * 1_000_000_000)
+            .build());
+        // Nothing unlocks after the termination timestamp.
+        contract.claim(None);
+        assert_eq!(
+            contract.lockups.get(0).unwrap().claimed_balance,
+            NearToken::from_yoctonear(300)
+        );
+
+        contract.finalize_termination(0);
+        let lockup = contract.lockups.get(0).unwrap();
+        assert_eq!(lockup.schedule.total_balance(), lockup.claimed_balance);
+        let refund: Vec<serde_json::Value> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::MockAction::FunctionCallWeight {
+                    method_name, args, ..
+                } if method_name == b"ft_transfer" => Some(serde_json::from_slice(&args).unwrap()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(refund.len(), 2);
+        assert_eq!(refund[0]["amount"], "300");
+        assert_eq!(refund[1]["receiver_id"], "p.near");
+        assert_eq!(refund[1]["amount"], "700");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "lockup 0 can't be claimed while a bad leaver termination is pending"
+    )]
+    fn test_claim_pending_bad_leaver_termination() {
+        let (mut contract, mut context) = contract_with_scheduled_termination(Leaver::Bad);
+        testing_env!(context
+            .predecessor_account_id("x.near".parse().unwrap())
+            .block_timestamp(200 * 1_000_000_000)
+            .build());
+        // Nothing is claimable while the termination is pending.
+        assert!(matches!(
+            contract.claim(None),
+            PromiseOrValue::Value(amount) if amount == ZERO_NEAR
+        ));
+        contract.claim(Some(vec![(0, Some(NearToken::from_yoctonear(100)))]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the terminator can terminate the lockup")]
+    fn test_terminate_by_other_allowlisted_account() {
+        let mut context = VMContextBuilder::new();
+        let mut contract =
+            contract_with_lockups(&mut context, &terminable_lockup_create(), &[accounts(1)]);
+        let termination_config = contract.lockups.get(0).unwrap().termination_config.unwrap();
+        require!(termination_config.terminator_id == accounts(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.terminate(0, None, None, None);
+    }
+
+    #[test]
+    fn test_scheduled_termination() {
+        let mut context = VMContextBuilder::new();
+        let mut contract =
+            contract_with_lockups(&mut context, &terminable_lockup_create(), &[accounts(1)]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_timestamp(100 * 1_000_000_000)
+            .build());
+        contract.schedule_termination(0, None, U128(300), None);
+        let pending_termination = contract.get_lockup(0).unwrap().pending_termination.unwrap();
+        assert_eq!(pending_termination.announced_timestamp, U128(100));
+        assert_eq!(pending_termination.termination_timestamp, U128(300));
+
+        // Finalized by anyone once effective.
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(ZERO_NEAR)
+            .block_timestamp(400 * 1_000_000_000)
+            .build());
+        contract.finalize_termination(0);
+        let lockup = contract.get_lockup(0).unwrap();
+        assert_eq!(lockup.total_balance, NearToken::from_yoctonear(300));
+        assert!(lockup.termination_config.is_none());
+        assert!(lockup.pending_termination.is_none());
+    }
+
+    #[test]
+    fn test_terminate_many() {
+        let mut context = VMContextBuilder::new();
+        let lockup_create = LockupCreate {
+            terminator_id: Some(accounts(1)),
+            ..terminable_lockup_create()
+        };
+        let mut contract = contract_with_lockups(
+            &mut context,
+            &lockup_create,
+            &[accounts(1), accounts(1), accounts(2)],
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .block_timestamp(400 * 1_000_000_000)
+            .build());
+        contract.terminate_many(vec![(0, None), (1, None), (2, None)], None);
+        for index in 0..3 {
+            let lockup = contract.get_lockup(index).unwrap();
+            assert_eq!(lockup.total_balance, NearToken::from_yoctonear(400));
+            assert!(lockup.termination_config.is_none());
+        }
+        let logs = near_sdk::test_utils::get_logs();
+        assert_eq!(logs.len(), 3);
+        assert!(logs[2].contains(r#""event":"ft_lockup_terminate_lockup""#));
+        // A single transfer per beneficiary.
+        let transfers = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter(|action| {
+                matches!(
+                    action,
+                    near_sdk::mock::MockAction::FunctionCallWeight { method_name, .. }
+                        if method_name == b"ft_transfer"
+                )
+            })
+            .count();
+        assert_eq!(transfers, 2);
+
+        // A failed transfer rolls the terminations back.
+        let mut ft = FtMock::new(3000);
+        ft.failing_method = Some("ft_transfer");
+        assert_eq!(ft.resolve(&mut contract, &mut context), ZERO_NEAR);
+        for index in 0..3 {
+            let lockup = contract.get_lockup(index).unwrap();
+            assert_eq!(lockup.total_balance, NearToken::from_yoctonear(1000));
+            assert!(lockup.termination_config.is_some());
+        }
+        assert_eq!(contract.get_num_lockups(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected at most 100 lockups")]
+    fn test_terminate_many_too_many_lockups() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = contract_with_lockups(&mut context, &terminable_lockup_create(), &[]);
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.terminate_many((0..101).map(|index| (index, None)).collect(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough gas for 18 refunds of 18 lockups, 308 TGas required")]
+    fn test_terminate_many_too_many_beneficiaries() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.block_timestamp(400 * 1_000_000_000).build());
+        let mut contract = Contract::new(accounts(0), vec![], None);
+        for index in 0..18 {
+            let mut lockup = terminable_lockup();
+            lockup.termination_config.as_mut().unwrap().beneficiary_id =
+                format!("b{}.near", index).parse().unwrap();
+            contract.internal_add_lockup(&lockup);
+        }
+        testing_env!(context
+            .predecessor_account_id("p.near".parse().unwrap())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(near_sdk::Gas::from_tgas(300))
+            .build());
+        contract.terminate_many((0..18).map(|index| (index, None)).collect(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate lockup index: 0")]
+    fn test_terminate_many_duplicate_lockup() {
+        let mut context = VMContextBuilder::new();
+        let mut contract =
+            contract_with_lockups(&mut context, &terminable_lockup_create(), &[accounts(1)]);
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.terminate_many(vec![(0, None), (0, None)], None);
+    }
+
+    #[test]
+    fn test_terminate_disposition() {
+        let treasury_id: AccountId = "treasury.near".parse().unwrap();
+        for (disposition, receiver_id, method_name) in [
+            (
+                TerminationDisposition::Treasury {
+                    account_id: treasury_id.clone(),
+                },
+                Some(treasury_id.clone()),
+                "ft_transfer",
+            ),
+            (TerminationDisposition::Burn, None, "ft_burn"),
+        ] {
+            let mut context = VMContextBuilder::new();
+            let lockup_create = LockupCreate {
+                termination_disposition: Some(disposition),
+                ..terminable_lockup_create()
+            };
+            let mut contract = contract_with_lockups(&mut context, &lockup_create, &[accounts(1)]);
+
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(NearToken::from_yoctonear(1))
+                .block_timestamp(400 * 1_000_000_000)
+                .build());
+            contract.terminate(0, None, None, None);
+            let actions: Vec<(String, serde_json::Value)> =
+                near_sdk::test_utils::get_created_receipts()
+                    .into_iter()
+                    .filter(|receipt| receipt.receiver_id == accounts(0))
+                    .flat_map(|receipt| receipt.actions)
+                    .filter_map(|action| match action {
+                        near_sdk::mock::MockAction::FunctionCallWeight {
+                            method_name,
+                            args,
+                            ..
+                        } => Some((
+                            String::from_utf8(method_name).unwrap(),
+                            serde_json::from_slice(&args).unwrap(),
+                        )),
+                        _ => None,
+                    })
+                    .collect();
+            assert_eq!(actions[0].0, method_name);
+            assert_eq!(
+                actions[0].1["receiver_id"].as_str(),
+                receiver_id.as_ref().map(|account_id| account_id.as_str())
+            );
+            assert_eq!(actions[0].1["amount"], "600");
+
+            // A failed refund rolls the termination back, with either entry point.
+            let mut ft = FtMock::new(1000);
+            ft.failing_method = Some(method_name);
+            assert_eq!(ft.resolve(&mut contract, &mut context), ZERO_NEAR);
+            testing_env!(context.predecessor_account_id(accounts(1)).build());
+            let lockup = contract.get_lockup(0).unwrap();
+            assert_eq!(lockup.total_balance, NearToken::from_yoctonear(1000));
+            assert!(lockup.termination_config.is_some());
+
+            contract.terminate_many(vec![(0, None)], None);
+            assert_eq!(ft.resolve(&mut contract, &mut context), ZERO_NEAR);
+            testing_env!(context.predecessor_account_id(accounts(1)).build());
+            let lockup = contract.get_lockup(0).unwrap();
+            assert_eq!(lockup.total_balance, NearToken::from_yoctonear(1000));
+            assert!(lockup.termination_config.is_some());
+            assert_eq!(contract.get_num_lockups(), 1);
+
+            // Terminated again, the refund goes to its disposition.
+            ft.failing_method = None;
+            contract.terminate(0, None, None, None);
+            assert_eq!(
+                ft.resolve(&mut contract, &mut context),
+                NearToken::from_yoctonear(600)
+            );
+            match receiver_id {
+                Some(treasury_id) => assert_eq!(ft.balance_of(&treasury_id), 600),
+                None => assert_eq!(ft.total_burned, 600),
+            }
+            assert_eq!(ft.balance_of(&accounts(0)), 400);
+        }
+    }
 }
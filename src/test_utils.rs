@@ -0,0 +1,169 @@
+use crate::{
+    callbacks::SelfCallbacks,
+    lockup::{Lockup, LockupCreate},
+    schedule::Schedule,
+    termination::VestingConditions,
+    Contract,
+};
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::{
+    env,
+    json_types::U128,
+    mock::MockAction,
+    serde_json,
+    test_utils::{accounts, VMContextBuilder},
+    test_vm_config, testing_env, AccountId, NearToken, PromiseResult, RuntimeFeesConfig,
+};
+use std::collections::HashMap;
+
+/// The lockup of 1000 for `x.near`, unlocked linearly over the first 1000 seconds
+/// and vested the same way.
+pub(crate) fn terminable_lockup_create() -> LockupCreate {
+    let total_balance = NearToken::from_yoctonear(1000);
+    LockupCreate {
+        schedule: Schedule::new_cliff_linear(total_balance, 0.into(), 0.into(), 1000.into()).into(),
+        vesting_schedule: Some(VestingConditions::SameAsLockupSchedule),
+        ..LockupCreate::new_unlocked("x.near".parse().unwrap(), total_balance)
+    }
+}
+
+/// The lockup of `terminable_lockup_create` paid for by `p.near`, its beneficiary and terminator.
+pub(crate) fn terminable_lockup() -> Lockup {
+    terminable_lockup_create().into_lockup(&"p.near".parse().unwrap())
+}
+
+/// The contract of the token `accounts(0)`, with `accounts(1)` and `accounts(2)` allowlisted.
+/// The lockup is created by a transfer from every one of `sender_ids`, so the sender
+/// is the beneficiary and the terminator of its lockup.
+pub(crate) fn contract_with_lockups(
+    context: &mut VMContextBuilder,
+    lockup_create: &LockupCreate,
+    sender_ids: &[AccountId],
+) -> Contract {
+    testing_env!(context
+        .current_account_id(accounts(0))
+        .predecessor_account_id(accounts(0))
+        .build());
+    let mut contract = Contract::new(accounts(0), vec![accounts(1), accounts(2)], None);
+    let amount = U128(
+        lockup_create
+            .schedule
+            .resolve(None)
+            .unwrap()
+            .total_balance()
+            .as_yoctonear(),
+    );
+    for sender_id in sender_ids {
+        contract.ft_on_transfer(
+            sender_id.clone(),
+            amount,
+            serde_json::to_string(lockup_create).unwrap(),
+        );
+    }
+    contract
+}
+
+/// A fake fungible token for the unit tests. It executes the token calls of the last contract
+/// call against its balances and feeds the results into the callback of the contract.
+pub(crate) struct FtMock {
+    pub balances: HashMap<AccountId, u128>,
+    pub total_burned: u128,
+    /// The token method which fails, e.g. `ft_burn`.
+    pub failing_method: Option<&'static str>,
+}
+
+impl FtMock {
+    /// The token with the whole given balance held by the lockup contract.
+    pub fn new(contract_balance: u128) -> Self {
+        Self {
+            balances: HashMap::from([(env::current_account_id(), contract_balance)]),
+            total_burned: 0,
+            failing_method: None,
+        }
+    }
+
+    pub fn balance_of(&self, account_id: &AccountId) -> u128 {
+        self.balances.get(account_id).copied().unwrap_or_default()
+    }
+
+    /// Executes the token calls, then the callback of the contract and returns its value.
+    pub fn resolve(
+        &mut self,
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+    ) -> NearToken {
+        let contract_id = env::current_account_id();
+        let mut results = vec![];
+        let mut callback = None;
+        for receipt in near_sdk::test_utils::get_created_receipts() {
+            for action in receipt.actions {
+                let MockAction::FunctionCallWeight {
+                    method_name, args, ..
+                } = action
+                else {
+                    continue;
+                };
+                let method_name = String::from_utf8(method_name).unwrap();
+                let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+                // The tests often use the lockup contract account as the token id too.
+                if method_name.starts_with("ft_") {
+                    assert_eq!(receipt.receiver_id, contract.token_id);
+                    results.push(self.execute(&contract_id, &method_name, &args));
+                } else {
+                    assert_eq!(receipt.receiver_id, contract_id);
+                    assert!(callback.is_none(), "more than one callback");
+                    callback = Some((method_name, args));
+                }
+            }
+        }
+        let (method_name, args) = callback.expect("no callback");
+
+        testing_env!(
+            context.predecessor_account_id(contract_id).build(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            results
+        );
+        fn arg<T: near_sdk::serde::de::DeserializeOwned>(
+            args: &serde_json::Value,
+            name: &str,
+        ) -> T {
+            serde_json::from_value(args[name].clone()).unwrap()
+        }
+        match method_name.as_str() {
+            "after_ft_transfer" => {
+                contract.after_ft_transfer(arg(&args, "account_id"), arg(&args, "lockup_claims"))
+            }
+            "after_lockups_termination" => {
+                contract.after_lockups_termination(arg(&args, "refunds"))
+            }
+            _ => panic!("unexpected callback {}", method_name),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        sender_id: &AccountId,
+        method_name: &str,
+        args: &serde_json::Value,
+    ) -> PromiseResult {
+        if self.failing_method == Some(method_name) {
+            return PromiseResult::Failed;
+        }
+        let amount: u128 = args["amount"].as_str().unwrap().parse().unwrap();
+        let sender_balance = self.balances.get_mut(sender_id).unwrap();
+        *sender_balance = sender_balance
+            .checked_sub(amount)
+            .expect("not enough balance");
+        match method_name {
+            "ft_transfer" => {
+                let receiver_id = serde_json::from_value(args["receiver_id"].clone()).unwrap();
+                *self.balances.entry(receiver_id).or_default() += amount;
+            }
+            "ft_burn" => self.total_burned += amount,
+            _ => panic!("unexpected token method {}", method_name),
+        }
+        PromiseResult::Successful(vec![])
+    }
+}
@@ -1,6 +1,88 @@
-use near_sdk::{json_types::U128, NearToken, Timestamp};
+use near_sdk::{json_types::U128, near, require, NearToken, Timestamp};
 
 pub(crate) const ZERO_NEAR: NearToken = NearToken::from_near(0);
+
+/// Like `require!`, but returns the message as an error, so the caller can recover from
+/// an invalid input, e.g. by refunding the transfer.
+macro_rules! ensure {
+    ($cond:expr, $message:expr $(,)?) => {
+        if !$cond {
+            return Err($message.to_string());
+        }
+    };
+}
+pub(crate) use ensure;
+
+/// Panics with the error of a validation the caller can't recover from.
+pub(crate) fn expect_valid<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|message| near_sdk::env::panic_str(&message))
+}
+
+/// The rounding policy of the unlocked balance between checkpoints, chosen per contract.
+/// `Floor` never unlocks a fraction of a yocto ahead of the schedule, `Ceil` never holds
+/// a fraction of a yocto behind it. Balances at the checkpoints are always exact.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Rounding {
+    #[default]
+    Floor,
+    Ceil,
+}
+
+const LOW_MASK: u128 = u64::MAX as u128;
+
+/// Computes `a * b / denominator` with a 256-bit intermediate product, so it can't overflow
+/// as long as the result fits into u128.
+pub fn mul_div(a: u128, b: u128, denominator: u128, rounding: Rounding) -> u128 {
+    require!(denominator > 0, "division by zero");
+    let (quotient, remainder) = match a.checked_mul(b) {
+        Some(product) => (product / denominator, product % denominator),
+        None => {
+            let (high, low) = full_mul(a, b);
+            require!(high < denominator, "multiplication overflow");
+            div_rem_256(high, low, denominator)
+        }
+    };
+    match rounding {
+        Rounding::Ceil if remainder > 0 => quotient
+            .checked_add(1)
+            .unwrap_or_else(|| near_sdk::env::panic_str("multiplication overflow")),
+        _ => quotient,
+    }
+}
+
+/// Returns the high and the low 128 bits of the product.
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    let (a_high, a_low) = (a >> 64, a & LOW_MASK);
+    let (b_high, b_low) = (b >> 64, b & LOW_MASK);
+    let low_low = a_low * b_low;
+    let low_high = a_low * b_high;
+    let high_low = a_high * b_low;
+    let high_high = a_high * b_high;
+    // Fits into 66 bits.
+    let middle = (low_low >> 64) + (low_high & LOW_MASK) + (high_low & LOW_MASK);
+    let low = (low_low & LOW_MASK) | (middle << 64);
+    let high = high_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+    (high, low)
+}
+
+/// Long division of a 256-bit number by a 128-bit one. Assumes `high < denominator`,
+/// so the quotient fits into u128.
+fn div_rem_256(high: u128, low: u128, denominator: u128) -> (u128, u128) {
+    let mut remainder = high;
+    let mut quotient = 0;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        // With the carry the actual remainder is above 2^128, so it's above the denominator.
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    (quotient, remainder)
+}
 pub(crate) fn nano_to_sec(timestamp: Timestamp) -> u128 {
     (timestamp / 10u64.pow(9)) as u128
 }
@@ -9,6 +91,47 @@ pub fn current_timestamp_sec() -> U128 {
     U128(nano_to_sec(near_sdk::env::block_timestamp()))
 }
 
+pub(crate) fn is_leap_year(year: u128) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+pub(crate) fn days_in_month(year: u128, month: u128) -> u128 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days since the unix epoch of the given UTC date in the Gregorian calendar.
+/// Assumes the date is not before 1970-01-01.
+/// See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+pub(crate) fn days_from_civil(year: u128, month: u128, day: u128) -> u128 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The UTC date `(year, month, day)` of the given number of days since the unix epoch.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub(crate) fn civil_from_days(days: u128) -> (u128, u128, u128) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = (shifted_month + 2) % 12 + 1;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -18,6 +141,126 @@ mod tests {
         assert_eq!(nano_to_sec(1_719_234_571_328_277_000), 1_719_234_571);
     }
 
+    #[test]
+    fn test_mul_div_small() {
+        assert_eq!(mul_div(7, 3, 2, Rounding::Floor), 10);
+        assert_eq!(mul_div(7, 3, 2, Rounding::Ceil), 11);
+        assert_eq!(mul_div(8, 3, 2, Rounding::Ceil), 12);
+        assert_eq!(mul_div(0, 3, 2, Rounding::Ceil), 0);
+    }
+
+    #[test]
+    fn test_mul_div_wide() {
+        let max = u128::MAX;
+        assert_eq!(mul_div(max, max, max, Rounding::Floor), max);
+        assert_eq!(mul_div(max, max - 1, max, Rounding::Ceil), max - 1);
+        assert_eq!(mul_div(max, 3, 4, Rounding::Floor), max / 4 * 3 + 2);
+        assert_eq!(mul_div(max, 3, 4, Rounding::Ceil), max / 4 * 3 + 3);
+        // 2^127 * 2^64 / 2^100 = 2^91
+        assert_eq!(
+            mul_div(1 << 127, 1 << 64, 1 << 100, Rounding::Floor),
+            1 << 91
+        );
+        // (2^128 - 1) * (2^64 + 1) / 2^65 = 2^127 + 2^63 - (2^64 + 1) / 2^65
+        assert_eq!(
+            mul_div(max, (1 << 64) + 1, 1 << 65, Rounding::Floor),
+            (1 << 127) + (1 << 63) - 1
+        );
+        // Every combination of extreme values matches the narrow computation where it fits.
+        let values = [
+            1,
+            2,
+            3,
+            1 << 63,
+            u64::MAX as u128,
+            1 << 64,
+            1 << 100,
+            max - 1,
+            max,
+        ];
+        for &a in &values {
+            for &b in &values {
+                for &denominator in &values {
+                    if let Some(product) = a.checked_mul(b) {
+                        assert_eq!(
+                            mul_div(a, b, denominator, Rounding::Floor),
+                            product / denominator
+                        );
+                        assert_eq!(
+                            mul_div(a, b, denominator, Rounding::Ceil),
+                            product.div_ceil(denominator)
+                        );
+                    }
+                }
+            }
+        }
+        // The products of `a` and `denominator - 1`, `denominator` or `denominator + 1`
+        // mostly overflow u128, their exact quotients are `a - a / denominator`, `a`
+        // and `a + a / denominator`.
+        for &a in &values {
+            for &denominator in &values {
+                let below = denominator - 1;
+                assert_eq!(
+                    mul_div(a, below, denominator, Rounding::Floor),
+                    a - a.div_ceil(denominator)
+                );
+                assert_eq!(
+                    mul_div(a, below, denominator, Rounding::Ceil),
+                    a - a / denominator
+                );
+                for rounding in [Rounding::Floor, Rounding::Ceil] {
+                    assert_eq!(mul_div(a, denominator, denominator, rounding), a);
+                }
+                let Some(above) = denominator.checked_add(1) else {
+                    continue;
+                };
+                if let Some(ceil) = a.checked_add(a.div_ceil(denominator)) {
+                    assert_eq!(
+                        mul_div(a, above, denominator, Rounding::Floor),
+                        a + a / denominator
+                    );
+                    assert_eq!(mul_div(a, above, denominator, Rounding::Ceil), ceil);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic = "multiplication overflow"]
+    fn test_mul_div_overflow() {
+        mul_div(u128::MAX, u128::MAX, u128::MAX - 1, Rounding::Floor);
+    }
+
+    #[test]
+    fn test_calendar() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(2024, 2, 29), 19_782);
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(19_783), (2024, 3, 1));
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2023, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(2100, 12), 31);
+        assert_eq!(days_in_month(2100, 11), 30);
+
+        let (mut year, mut month, mut day) = (1970, 1, 1);
+        for days in 0..200_000 {
+            assert_eq!(civil_from_days(days), (year, month, day));
+            assert_eq!(days_from_civil(year, month, day), days);
+            day += 1;
+            if day > days_in_month(year, month) {
+                day = 1;
+                month += 1;
+            }
+            if month > 12 {
+                month = 1;
+                year += 1;
+            }
+        }
+    }
+
     #[test]
     fn test_current_timestamp_sec() {
         // env is working on a fresh blockchain starting from time 0
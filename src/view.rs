@@ -1,51 +1,75 @@
 use crate::{
-    lockup::{Lockup, LockupCreate, LockupIndex},
-    schedule::Schedule,
-    termination::{TerminationConfig, VestingConditions},
-    util::{current_timestamp_sec, ZERO_NEAR},
+    lockup::{Lockup, LockupCreate, LockupIndex, PausedInterval},
+    merkle::{merkle_leaf, merkle_proof, merkle_root},
+    migration::MigrationProgress,
+    schedule::{ScheduleAnchor, ScheduleSpec},
+    template::{ScheduleTemplate, TemplateId},
+    termination::{
+        signed_schedule_message, Leaver, PendingTermination, ScheduleReveal, TerminationConfig,
+        TerminationDisposition, TerminationOutcome, TerminationPolicy, VestingConditions,
+    },
+    util::{current_timestamp_sec, expect_valid, Rounding, ZERO_NEAR},
     Contract, ContractExt, VERSION,
 };
 use near_sdk::{
-    json_types::{Base58CryptoHash, U128},
-    near, AccountId, NearToken,
+    json_types::{Base58CryptoHash, Base64VecU8, U128},
+    near, AccountId, CryptoHash, NearToken,
 };
 
 #[near(serializers = [borsh, json])]
 #[derive(Debug)]
 pub struct LockupView {
     pub account_id: AccountId,
-    pub schedule: Schedule,
+    pub schedule: ScheduleSpec,
     pub claimed_balance: NearToken,
     /// An optional configuration that allows vesting/lockup termination.
     pub termination_config: Option<TerminationConfig>,
+    pub anchor: ScheduleAnchor,
+    pub pending_termination: Option<PendingTermination>,
+    pub paused_intervals: Vec<PausedInterval>,
+    /// Whether the timestamp the schedule is relative to is known.
+    pub anchor_set: bool,
 
     pub total_balance: NearToken,
     pub unclaimed_balance: NearToken,
+    /// The balance that stays with the account on termination,
+    /// `None` while the vesting schedule is hidden.
+    pub vested_balance: Option<NearToken>,
     /// The current timestamp
     pub timestamp: U128,
 }
 
-impl From<Lockup> for LockupView {
-    fn from(lockup: Lockup) -> Self {
+impl From<(Lockup, &Contract)> for LockupView {
+    fn from((lockup, contract): (Lockup, &Contract)) -> Self {
         let total_balance = lockup.schedule.total_balance();
         let timestamp = current_timestamp_sec();
         let unclaimed_balance = lockup
-            .schedule
-            .unlocked_balance(timestamp)
+            .unlocked_balance(timestamp, contract.rounding, contract.tge_timestamp)
             .saturating_sub(lockup.claimed_balance);
+        let vested_balance =
+            lockup.vested_balance(timestamp, contract.rounding, contract.tge_timestamp);
+        let anchor_set = lockup.schedule_offset(contract.tge_timestamp).is_some();
         let Lockup {
             account_id,
             schedule,
             claimed_balance,
             termination_config,
+            anchor,
+            pending_termination,
+            paused_intervals,
         } = lockup;
         Self {
             account_id,
             schedule,
             claimed_balance,
             termination_config,
+            anchor,
+            pending_termination,
+            paused_intervals,
+            anchor_set,
             total_balance,
             unclaimed_balance,
+            vested_balance,
             timestamp,
         }
     }
@@ -54,8 +78,14 @@ impl From<Lockup> for LockupView {
 #[near(serializers = [borsh, json])]
 pub struct LockupCreateView {
     pub account_id: AccountId,
-    pub schedule: Schedule,
+    pub schedule: ScheduleSpec,
     pub vesting_schedule: Option<VestingConditions>,
+    pub anchor: ScheduleAnchor,
+    pub beneficiary_id: Option<AccountId>,
+    pub terminator_id: Option<AccountId>,
+    pub termination_policy: Option<TerminationPolicy>,
+    pub termination_disposition: Option<TerminationDisposition>,
+    pub refund_excess: bool,
 
     pub claimed_balance: NearToken,
     pub total_balance: NearToken,
@@ -64,20 +94,33 @@ pub struct LockupCreateView {
     pub timestamp: U128,
 }
 
-impl From<LockupCreate> for LockupCreateView {
-    fn from(lockup_create: LockupCreate) -> Self {
-        let total_balance = lockup_create.schedule.total_balance();
+impl From<(LockupCreate, Rounding)> for LockupCreateView {
+    fn from((lockup_create, rounding): (LockupCreate, Rounding)) -> Self {
+        let schedule = expect_valid(lockup_create.schedule.resolve(None));
+        let total_balance = schedule.total_balance();
         let timestamp = current_timestamp_sec();
-        let unclaimed_balance = lockup_create.schedule.unlocked_balance(timestamp);
+        let unclaimed_balance = schedule.unlocked_balance(timestamp, rounding);
         let LockupCreate {
             account_id,
-            schedule,
+            schedule: _,
             vesting_schedule,
+            anchor,
+            beneficiary_id,
+            terminator_id,
+            termination_policy,
+            termination_disposition,
+            refund_excess,
         } = lockup_create;
         Self {
             account_id,
             schedule,
             vesting_schedule,
+            anchor,
+            beneficiary_id,
+            terminator_id,
+            termination_policy,
+            termination_disposition,
+            refund_excess,
             claimed_balance: ZERO_NEAR,
             total_balance,
             unclaimed_balance,
@@ -86,6 +129,33 @@ impl From<LockupCreate> for LockupCreateView {
     }
 }
 
+/// The outcome of `terminate` with the same arguments.
+#[near(serializers = [json])]
+#[derive(Debug)]
+pub struct TerminationPreview {
+    pub termination_timestamp: U128,
+    /// The vested balance, it stays in the lockup except the forfeited part.
+    pub vested_balance: NearToken,
+    /// The balance that is transferred to the beneficiary together with the forfeited balance.
+    pub unvested_balance: NearToken,
+    /// The vested balance forfeited by a bad leaver.
+    pub forfeited_balance: NearToken,
+    pub beneficiary_id: AccountId,
+    /// Where the unvested and the forfeited balances go.
+    pub disposition: TerminationDisposition,
+    /// The lockup schedule after the termination.
+    pub schedule: ScheduleSpec,
+}
+
+/// A Merkle tree of the vesting schedules of a cohort for `VestingConditions::MerkleRoot`.
+#[near(serializers = [json])]
+#[derive(Debug)]
+pub struct MerkleTreeView {
+    pub root: Base58CryptoHash,
+    /// The proofs in the order of the given schedules.
+    pub proofs: Vec<Vec<Base58CryptoHash>>,
+}
+
 #[near]
 impl Contract {
     pub fn get_token_id(&self) -> AccountId {
@@ -95,12 +165,13 @@ impl Contract {
     pub fn get_account_lockups(&self, account_id: AccountId) -> Vec<(LockupIndex, LockupView)> {
         self.internal_get_account_lockups(&account_id)
             .into_iter()
-            .map(|(lockup_index, lockup)| (lockup_index, lockup.into()))
+            .map(|(lockup_index, lockup)| (lockup_index, (lockup, self).into()))
             .collect()
     }
 
     pub fn get_lockup(&self, index: LockupIndex) -> Option<LockupView> {
-        self.lockups.get(index as _).map(|lockup| lockup.into())
+        self.internal_get_lockup(index)
+            .map(|lockup| (lockup, self).into())
     }
 
     pub fn get_lockups(&self, indices: Vec<LockupIndex>) -> Vec<(LockupIndex, LockupView)> {
@@ -126,19 +197,125 @@ impl Contract {
             .collect()
     }
 
+    pub fn get_rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    pub fn get_tge_timestamp(&self) -> Option<U128> {
+        self.tge_timestamp.map(U128)
+    }
+
+    pub fn get_migration(&self) -> Option<MigrationProgress> {
+        self.migration.clone()
+    }
+
     pub fn get_deposit_allowlist(&self) -> Vec<AccountId> {
         self.deposit_allowlist.to_vec()
     }
 
-    pub fn hash_schedule(&self, schedule: Schedule) -> Base58CryptoHash {
+    pub fn get_template(&self, template_id: TemplateId) -> Option<ScheduleTemplate> {
+        self.templates.get(&template_id)
+    }
+
+    pub fn get_templates(&self) -> Vec<(TemplateId, ScheduleTemplate)> {
+        self.templates.to_vec()
+    }
+
+    pub fn hash_schedule(&self, schedule: ScheduleSpec) -> Base58CryptoHash {
         schedule.hash().into()
     }
 
+    pub fn hash_schedule_salted(
+        &self,
+        schedule: ScheduleSpec,
+        salt: Base64VecU8,
+    ) -> Base58CryptoHash {
+        schedule.salted_hash(&salt.0).into()
+    }
+
+    /// The message to sign for the `VestingConditions::SignedBy` of the lockup.
+    pub fn get_signed_schedule_message(
+        &self,
+        lockup_index: LockupIndex,
+        schedule: ScheduleSpec,
+    ) -> Base58CryptoHash {
+        let lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        let nonce = match lockup
+            .termination_config
+            .map(|config| config.vesting_schedule)
+        {
+            Some(VestingConditions::SignedBy { nonce, .. }) => Some(nonce),
+            _ => None,
+        }
+        .expect("The vesting schedule is not signed");
+        signed_schedule_message(&lockup.account_id, lockup_index, nonce, &schedule).into()
+    }
+
+    pub fn get_merkle_tree(&self, schedules: Vec<(AccountId, ScheduleSpec)>) -> MerkleTreeView {
+        let leaves: Vec<CryptoHash> = schedules
+            .iter()
+            .map(|(account_id, schedule)| merkle_leaf(account_id, schedule))
+            .collect();
+        MerkleTreeView {
+            root: merkle_root(&leaves).into(),
+            proofs: (0..leaves.len())
+                .map(|index| {
+                    merkle_proof(&leaves, index)
+                        .into_iter()
+                        .map(Base58CryptoHash::from)
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Runs the termination on a copy of the lockup. Fails the same way as `terminate`,
+    /// except that it can be called by any account.
+    pub fn preview_termination(
+        &self,
+        lockup_index: LockupIndex,
+        hashed_schedule: Option<ScheduleReveal>,
+        termination_timestamp: Option<U128>,
+        policy: Option<Leaver>,
+    ) -> TerminationPreview {
+        let mut lockup = self
+            .internal_get_lockup(lockup_index)
+            .expect("Lockup not found");
+        let termination_timestamp = self.internal_termination_timestamp(termination_timestamp);
+        let TerminationOutcome {
+            unvested_balance,
+            forfeited_balance,
+            beneficiary_id,
+            disposition,
+        } = lockup.terminate_as(
+            lockup_index,
+            policy.unwrap_or_default(),
+            hashed_schedule,
+            termination_timestamp,
+            self.rounding,
+            self.tge_timestamp,
+        );
+        TerminationPreview {
+            termination_timestamp,
+            vested_balance: lockup
+                .schedule
+                .total_balance()
+                .saturating_add(forfeited_balance),
+            unvested_balance,
+            forfeited_balance,
+            beneficiary_id,
+            disposition,
+            schedule: lockup.schedule,
+        }
+    }
+
     pub fn validate_schedule(
         &self,
-        schedule: Schedule,
+        schedule: ScheduleSpec,
         total_balance: NearToken,
-        termination_schedule: Option<Schedule>,
+        termination_schedule: Option<ScheduleSpec>,
     ) {
         schedule.assert_valid(total_balance);
         if let Some(termination_schedule) = termination_schedule {
@@ -154,9 +331,14 @@ impl Contract {
 
 #[cfg(test)]
 mod tests {
-    use crate::Checkpoint;
+    use crate::{
+        schedule::{Interpolation, ScheduleAnchor},
+        test_utils::terminable_lockup,
+        Checkpoint, Schedule,
+    };
 
     use super::*;
+    use near_sdk::{test_utils::VMContextBuilder, testing_env};
 
     #[test]
     fn test_nano_to_sec() {
@@ -166,22 +348,63 @@ mod tests {
             Checkpoint {
                 timestamp: 100,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: 200,
                 balance: amount,
+                interpolation: Interpolation::Linear,
             },
         ]);
         schedule.assert_valid(amount);
         let lockup_create = LockupCreate {
             account_id,
-            schedule: schedule.clone(),
+            schedule: schedule.clone().into(),
             vesting_schedule: None,
+            anchor: ScheduleAnchor::Absolute,
+            beneficiary_id: None,
+            terminator_id: None,
+            termination_policy: None,
+            termination_disposition: None,
+            refund_excess: false,
         };
         // let lockup = lockup_create.into_lockup(&"y.near".parse().unwrap());
-        let lockup_view = LockupCreateView::from(lockup_create);
+        let lockup_view = LockupCreateView::from((lockup_create, Rounding::Floor));
         assert_eq!(lockup_view.total_balance, amount);
         assert_eq!(lockup_view.claimed_balance, ZERO_NEAR);
         assert_eq!(lockup_view.unclaimed_balance, ZERO_NEAR);
     }
+
+    fn contract_with_terminable_lockup() -> Contract {
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(100 * 1_000_000_000)
+            .build());
+        let mut contract = Contract::new("token.near".parse().unwrap(), vec![], None);
+        contract.internal_add_lockup(&terminable_lockup());
+        contract
+    }
+
+    #[test]
+    fn test_preview_termination() {
+        let contract = contract_with_terminable_lockup();
+        let preview = contract.preview_termination(0, None, Some(U128(300)), None);
+        assert_eq!(preview.termination_timestamp, U128(300));
+        assert_eq!(preview.vested_balance, NearToken::from_yoctonear(300));
+        assert_eq!(preview.unvested_balance, NearToken::from_yoctonear(700));
+        assert_eq!(preview.beneficiary_id.as_str(), "p.near");
+        assert_eq!(preview.schedule.total_balance(), preview.vested_balance);
+        assert_eq!(preview.schedule.finish_timestamp(), 300);
+
+        // The lockup itself isn't terminated.
+        let lockup = contract.get_lockup(0).unwrap();
+        assert_eq!(lockup.total_balance, NearToken::from_yoctonear(1000));
+        assert!(lockup.termination_config.is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected termination_timestamp >= now")]
+    fn test_preview_termination_in_the_past() {
+        let contract = contract_with_terminable_lockup();
+        contract.preview_termination(0, None, Some(U128(50)), None);
+    }
 }
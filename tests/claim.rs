@@ -3,6 +3,7 @@ mod setup;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::setup::*;
+use ft_lockup::schedule::ScheduleAnchor;
 use near_sdk::NearToken;
 
 pub(crate) const ZERO_NEAR: NearToken = NearToken::from_near(0);
@@ -30,18 +31,26 @@ async fn test_lockup_claim_logic() {
         Checkpoint {
             timestamp: checkpoint_time,
             balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: checkpoint_time + 1,
             balance: amount,
+            interpolation: Interpolation::Linear,
         },
     ]);
     schedule.assert_valid(amount);
 
     let lockup_create = LockupCreate {
         account_id: users.alice.id().clone(),
-        schedule,
+        schedule: schedule.into(),
         vesting_schedule: None,
+        anchor: ScheduleAnchor::Absolute,
+        beneficiary_id: None,
+        terminator_id: None,
+        termination_policy: None,
+        termination_disposition: None,
+        refund_excess: false,
     };
     let balance = e.add_lockup(&e.owner, amount, &lockup_create).await.0;
     // refund amount from ft_transfer
@@ -119,13 +128,22 @@ async fn test_lockup_linear() {
             Checkpoint {
                 timestamp: genesis_timestamp,
                 balance: ZERO_NEAR,
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: genesis_timestamp + ONE_YEAR_SEC,
                 balance: amount,
+                interpolation: Interpolation::Linear,
             },
-        ]),
+        ])
+        .into(),
         vesting_schedule: None,
+        anchor: ScheduleAnchor::Absolute,
+        beneficiary_id: None,
+        terminator_id: None,
+        termination_policy: None,
+        termination_disposition: None,
+        refund_excess: false,
     };
     let balance = e.add_lockup(&e.owner, amount, &lockup_create).await;
     assert_eq!(balance.0, 0);
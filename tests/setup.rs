@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 pub use ft_lockup::{
     lockup::{LockupCreate, LockupIndex},
-    schedule::{Checkpoint, Schedule},
+    schedule::{Checkpoint, Interpolation, Schedule},
     view::LockupView,
 };
 use near_contract_standards::fungible_token::metadata::{FungibleTokenMetadata, FT_METADATA_SPEC};
@@ -70,36 +70,44 @@ pub fn lockup_vesting_schedule(amount: NearToken) -> (Schedule, Schedule) {
         Checkpoint {
             timestamp: 0,
             balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC * 2,
             balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC * 4,
             balance: amount.saturating_mul(3).saturating_div(4),
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC * 4 + 1,
             balance: amount,
+            interpolation: Interpolation::Linear,
         },
     ]);
     let vesting_schedule = Schedule(vec![
         Checkpoint {
             timestamp: 0,
             balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC - 1,
             balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC,
             balance: amount.saturating_div(4),
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC * 4,
             balance: amount,
+            interpolation: Interpolation::Linear,
         },
     ]);
     (lockup_schedule, vesting_schedule)
@@ -110,28 +118,34 @@ pub fn lockup_vesting_schedule_2(amount: NearToken) -> (Schedule, Schedule) {
         Checkpoint {
             timestamp: ONE_YEAR_SEC * 2,
             balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC * 4,
             balance: amount.saturating_mul(3).saturating_div(4),
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC * 4 + 1,
             balance: amount,
+            interpolation: Interpolation::Linear,
         },
     ]);
     let vesting_schedule = Schedule(vec![
         Checkpoint {
             timestamp: ONE_YEAR_SEC - 1,
             balance: ZERO_NEAR,
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC,
             balance: amount.saturating_div(4),
+            interpolation: Interpolation::Linear,
         },
         Checkpoint {
             timestamp: ONE_YEAR_SEC * 4,
             balance: amount,
+            interpolation: Interpolation::Linear,
         },
     ]);
     (lockup_schedule, vesting_schedule)
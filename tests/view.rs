@@ -40,10 +40,12 @@ mod e2e_view {
             Checkpoint {
                 timestamp: ONE_YEAR_SEC * 4,
                 balance: NearToken::from_near(0),
+                interpolation: Interpolation::Linear,
             },
             Checkpoint {
                 timestamp: ONE_YEAR_SEC * 4 + 1,
                 balance: amount,
+                interpolation: Interpolation::Linear,
             },
         ]);
         e.validate_schedule(